@@ -0,0 +1,192 @@
+// Loads every level in the level pack without starting the game, and checks
+// that it parses and that everything the player needs to get to is reachable
+// from the spawn point. Every level apart from the overworld also needs a goal
+// that can be reached, or it can never be completed.
+//
+// Usage: cargo run --bin validate_levels [assets directory]
+//
+// Exits with a non-zero status if any level fails.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use platformer::level::{Level, LevelContents, PortalTarget};
+use platformer::level_meta::LevelMeta;
+use platformer::level_pack::{LevelPackManifest, LEVEL_PACK_PATH};
+use platformer::reachability;
+
+fn main() -> ExitCode {
+    let assets_dir = std::env::args().nth(1).unwrap_or_else(|| "assets".to_string());
+    let manifest_path = Path::new(&assets_dir).join(LEVEL_PACK_PATH);
+    let manifest: LevelPackManifest = match std::fs::read_to_string(&manifest_path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| ron::from_str(&contents).map_err(|e| e.to_string()))
+    {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("could not read {}: {e}", manifest_path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    if manifest.levels.is_empty() {
+        eprintln!("{} doesn't list any levels", manifest_path.display());
+        return ExitCode::FAILURE;
+    }
+    // paired with whether each is the overworld
+    let level_paths: Vec<(PathBuf, bool)> = manifest
+        .overworld
+        .iter()
+        .map(|level_path| (level_path, true))
+        .chain(manifest.levels.iter().map(|entry| (&entry.path, false)))
+        .map(|(level_path, is_overworld)| {
+            (Path::new(&assets_dir).join(level_path), is_overworld)
+        })
+        .collect();
+
+    let mut any_failed = false;
+    for (level_path, is_overworld) in &level_paths {
+        match validate_level(
+            Path::new(&assets_dir),
+            &manifest,
+            level_path,
+            *is_overworld,
+        ) {
+            Ok(()) => println!("ok      {}", level_path.display()),
+            Err(problems) => {
+                any_failed = true;
+                println!("FAILED  {}", level_path.display());
+                for problem in problems {
+                    println!("        {problem}");
+                }
+            }
+        }
+    }
+
+    if any_failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn validate_level(
+    assets_dir: &Path,
+    manifest: &LevelPackManifest,
+    level_path: &Path,
+    is_overworld: bool,
+) -> Result<(), Vec<String>> {
+    let level_image = image::open(level_path)
+        .map_err(|e| vec![format!("could not read image: {e}")])?
+        .to_rgba8();
+    let meta = LevelMeta::try_read_sidecar(level_path)
+        .map_err(|e| vec![format!("could not parse metadata: {e}")])?
+        .unwrap_or_default();
+    let level = Level::from_rgba(&level_image, &meta)
+        .map_err(|e| vec![format!("parse error: {e:?}")])?;
+
+    let reachability = reachability::analyse(&level);
+    let mut problems: Vec<String> = level
+        .0
+        .iter()
+        .filter(|(position, contents)| {
+            reachability::is_target(contents) && !reachability.reaches(**position)
+        })
+        .map(|(position, contents)| {
+            format!(
+                "{} at ({}, {}) can't be reached from the spawn point",
+                describe(contents),
+                position.x,
+                position.y
+            )
+        })
+        .collect();
+
+    let has_goal = level.0.iter().any(|(position, contents)| {
+        matches!(contents, LevelContents::Goal) && reachability.reaches(*position)
+    });
+    if !is_overworld && !has_goal {
+        problems.push("there's no goal that can be reached".to_string());
+    }
+
+    let has_id = |id: &String| manifest.levels.iter().any(|entry| &entry.id == id);
+
+    // portals to other levels need to lead somewhere that exists, and gates need
+    // to depend on levels that exist
+    for level_contents in level.0.values() {
+        let (path, spawn) = match level_contents {
+            LevelContents::Portal {
+                target: PortalTarget::Level { path, spawn },
+                ..
+            } => (path, spawn),
+            LevelContents::Portal {
+                target: PortalTarget::LevelId(id),
+                ..
+            } => {
+                if !has_id(id) {
+                    problems.push(format!(
+                        "{} isn't in the level pack",
+                        describe(level_contents)
+                    ));
+                }
+                continue;
+            }
+            LevelContents::Gate { requires } => {
+                for id in requires.iter().filter(|id| !has_id(id)) {
+                    problems.push(format!(
+                        "{} requires level {id}, which isn't in the level pack",
+                        describe(level_contents)
+                    ));
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let target_path = assets_dir.join(path);
+        if !target_path.exists() {
+            problems.push(format!("{} doesn't exist", describe(level_contents)));
+            continue;
+        }
+        let Some(spawn) = spawn else { continue };
+        let has_spawn = LevelMeta::read_sidecar(&target_path)
+            .is_some_and(|target_meta| target_meta.spawn_points.contains(spawn));
+        if !has_spawn {
+            problems.push(format!(
+                "{} leads to spawn point {spawn}, which that level doesn't have",
+                describe(level_contents)
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        problems.sort();
+        Err(problems)
+    }
+}
+
+fn describe(level_contents: &LevelContents) -> String {
+    match level_contents {
+        LevelContents::Player => "player".to_string(),
+        LevelContents::Tile => "tile".to_string(),
+        LevelContents::Portal {
+            target: PortalTarget::Level { path, .. },
+            ..
+        } => format!("portal to {}", path.display()),
+        LevelContents::Portal {
+            target: PortalTarget::Spawn(spawn),
+            ..
+        } => format!("portal to spawn point {spawn}"),
+        LevelContents::Portal {
+            target: PortalTarget::LevelId(id),
+            ..
+        } => format!("portal to level {id}"),
+        LevelContents::SpawnPoint(spawn) => format!("spawn point {spawn}"),
+        LevelContents::Goal => "goal".to_string(),
+        LevelContents::Collectible => "collectible".to_string(),
+        LevelContents::Checkpoint => "checkpoint".to_string(),
+        LevelContents::ExtraLife => "extra life".to_string(),
+        LevelContents::Hazard(kind) => format!("{kind:?} hazard"),
+        LevelContents::Gate { .. } => "gate".to_string(),
+    }
+}
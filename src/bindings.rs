@@ -0,0 +1,343 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::save::SaveError;
+
+// Bump this whenever the format changes in a way that old files can't be read
+// as. Files with a different version are ignored and the defaults are used.
+// Adding a control doesn't need a bump, since controls missing from a file
+// get their default bindings when it's loaded.
+pub const BINDINGS_VERSION: u32 = 1;
+
+// under the user's config directory, eg ~/.config on linux
+const BINDINGS_DIR: &str = "jump-rectangle";
+const BINDINGS_FILE: &str = "bindings.ron";
+
+// Everything that can be rebound. Most are a GameAction or GlobalAction, but
+// GameAction::Move is split into its four directions. The analog sticks aren't
+// rebindable and always work as well.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Control {
+    // GameAction
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Debug,
+    Restart,
+    // GlobalAction
+    Start,
+    Select,
+    MenuUp,
+    MenuDown,
+    Confirm,
+    Back,
+    // frame-stepping while paused
+    StepPhysics,
+    StepInput,
+    DebugOverlay,
+}
+
+impl Control {
+    pub const ALL: [Control; 16] = [
+        Control::MoveUp,
+        Control::MoveDown,
+        Control::MoveLeft,
+        Control::MoveRight,
+        Control::Jump,
+        Control::Debug,
+        Control::Restart,
+        Control::Start,
+        Control::Select,
+        Control::MenuUp,
+        Control::MenuDown,
+        Control::Confirm,
+        Control::Back,
+        Control::StepPhysics,
+        Control::StepInput,
+        Control::DebugOverlay,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Control::MoveUp => "move up",
+            Control::MoveDown => "move down",
+            Control::MoveLeft => "move left",
+            Control::MoveRight => "move right",
+            Control::Jump => "jump",
+            Control::Debug => "debug",
+            Control::Restart => "restart",
+            Control::Start => "start",
+            Control::Select => "select",
+            Control::MenuUp => "menu up",
+            Control::MenuDown => "menu down",
+            Control::Confirm => "confirm",
+            Control::Back => "back",
+            Control::StepPhysics => "step",
+            Control::StepInput => "step input",
+            Control::DebugOverlay => "overlay",
+        }
+    }
+
+    fn group(self) -> ControlGroup {
+        match self {
+            Control::MoveUp
+            | Control::MoveDown
+            | Control::MoveLeft
+            | Control::MoveRight
+            | Control::Jump
+            | Control::Debug
+            | Control::Restart => ControlGroup::Game,
+            Control::MenuUp
+            | Control::MenuDown
+            | Control::Confirm
+            | Control::Back => ControlGroup::Menu,
+            Control::Start
+            | Control::Select
+            | Control::StepPhysics
+            | Control::StepInput
+            | Control::DebugOverlay => ControlGroup::Always,
+        }
+    }
+
+    // Game controls are only read while playing, and menu controls only while
+    // a menu is up, so one of each can share an input, eg space is both jump
+    // and confirm. Anything else would do two things at once.
+    fn can_share_input(self, other: Control) -> bool {
+        matches!(
+            (self.group(), other.group()),
+            (ControlGroup::Game, ControlGroup::Menu)
+                | (ControlGroup::Menu, ControlGroup::Game)
+        )
+    }
+}
+
+// When a control is read
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ControlGroup {
+    // while playing
+    Game,
+    // while a menu is up
+    Menu,
+    // at any time, like start pausing the game or the debug overlay
+    Always,
+}
+
+// Which key and gamepad button each Control is bound to. Loaded at startup and
+// written whenever it's changed on the settings screen.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Bindings {
+    pub version: u32,
+    pub keyboard: HashMap<Control, KeyCode>,
+    pub gamepad: HashMap<Control, GamepadButtonType>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            version: BINDINGS_VERSION,
+            keyboard: HashMap::from([
+                (Control::MoveUp, KeyCode::Up),
+                (Control::MoveDown, KeyCode::Down),
+                (Control::MoveLeft, KeyCode::Left),
+                (Control::MoveRight, KeyCode::Right),
+                (Control::Jump, KeyCode::Space),
+                (Control::Debug, KeyCode::Grave),
+                (Control::Restart, KeyCode::R),
+                (Control::Start, KeyCode::Return),
+                (Control::MenuUp, KeyCode::Up),
+                (Control::MenuDown, KeyCode::Down),
+                (Control::Confirm, KeyCode::Space),
+                (Control::Back, KeyCode::Escape),
+                (Control::StepPhysics, KeyCode::Period),
+                (Control::StepInput, KeyCode::Comma),
+                (Control::DebugOverlay, KeyCode::F3),
+            ]),
+            gamepad: HashMap::from([
+                (Control::MoveUp, GamepadButtonType::DPadUp),
+                (Control::MoveDown, GamepadButtonType::DPadDown),
+                (Control::MoveLeft, GamepadButtonType::DPadLeft),
+                (Control::MoveRight, GamepadButtonType::DPadRight),
+                (Control::Jump, GamepadButtonType::South),
+                (Control::Debug, GamepadButtonType::North),
+                (Control::Restart, GamepadButtonType::West),
+                (Control::Start, GamepadButtonType::Start),
+                (Control::Select, GamepadButtonType::Select),
+                (Control::MenuUp, GamepadButtonType::DPadUp),
+                (Control::MenuDown, GamepadButtonType::DPadDown),
+                (Control::Confirm, GamepadButtonType::South),
+                (Control::Back, GamepadButtonType::East),
+            ]),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(BINDINGS_DIR).join(BINDINGS_FILE))
+    }
+
+    // Falls back to the defaults if there's no file yet, or if it can't be read
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            warn!("no config directory, bindings won't be saved");
+            return Self::default();
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match ron::from_str::<Bindings>(&contents) {
+            Ok(mut bindings) if bindings.version == BINDINGS_VERSION => {
+                bindings.fill_in_defaults();
+                bindings
+            }
+            Ok(bindings) => {
+                warn!(
+                    "ignoring {} with unsupported version {}",
+                    path.display(),
+                    bindings.version
+                );
+                Self::default()
+            }
+            Err(e) => {
+                warn!("failed to parse {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    // Gives controls added since the file was written their default bindings,
+    // unless something the player bound is already using them
+    fn fill_in_defaults(&mut self) {
+        let defaults = Bindings::default();
+        for control in Control::ALL {
+            if !self.keyboard.contains_key(&control) {
+                if let Some(key) = defaults.keyboard.get(&control) {
+                    if let Err(other) = self.bind_key(control, *key) {
+                        warn!(
+                            "not binding {} to {key:?}, {} is using it",
+                            control.name(),
+                            other.name()
+                        );
+                    }
+                }
+            }
+            if !self.gamepad.contains_key(&control) {
+                if let Some(button) = defaults.gamepad.get(&control) {
+                    if let Err(other) = self.bind_button(control, *button) {
+                        warn!(
+                            "not binding {} to {button:?}, {} is using it",
+                            control.name(),
+                            other.name()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn write(&self) -> Result<(), SaveError> {
+        let path = Self::path().ok_or(SaveError::NoDataDir)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(SaveError::Io)?;
+        }
+        let contents = ron::ser::to_string_pretty(self, default())
+            .map_err(SaveError::Serialize)?;
+        std::fs::write(path, contents).map_err(SaveError::Io)
+    }
+
+    // Binds the key to the control, unless another control is already using
+    // it, in which case that control is returned
+    pub fn bind_key(
+        &mut self,
+        control: Control,
+        key: KeyCode,
+    ) -> Result<(), Control> {
+        Self::bind(&mut self.keyboard, control, key)
+    }
+
+    pub fn bind_button(
+        &mut self,
+        control: Control,
+        button: GamepadButtonType,
+    ) -> Result<(), Control> {
+        Self::bind(&mut self.gamepad, control, button)
+    }
+
+    fn bind<T: PartialEq>(
+        bound: &mut HashMap<Control, T>,
+        control: Control,
+        input: T,
+    ) -> Result<(), Control> {
+        let conflict = Control::ALL.into_iter().find(|other| {
+            *other != control
+                && !other.can_share_input(control)
+                && bound.get(other) == Some(&input)
+        });
+        match conflict {
+            Some(other) => Err(other),
+            None => {
+                bound.insert(control, input);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controls_in_the_same_group_cant_share_a_key() {
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            bindings.bind_key(Control::MenuUp, KeyCode::Down),
+            Err(Control::MenuDown)
+        );
+        assert_eq!(bindings.keyboard[&Control::MenuUp], KeyCode::Up);
+    }
+
+    #[test]
+    fn controls_read_while_playing_cant_share_a_key_with_game_controls() {
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            bindings.bind_key(Control::Start, KeyCode::Space),
+            Err(Control::Jump)
+        );
+        assert_eq!(
+            bindings.bind_button(Control::DebugOverlay, GamepadButtonType::South),
+            Err(Control::Jump)
+        );
+    }
+
+    #[test]
+    fn fills_in_controls_missing_from_a_file() {
+        let mut bindings = Bindings::default();
+        bindings.keyboard.remove(&Control::DebugOverlay);
+        bindings.keyboard.remove(&Control::StepPhysics);
+        bindings
+            .keyboard
+            .insert(Control::StepInput, KeyCode::Period);
+        bindings.gamepad.remove(&Control::Back);
+
+        bindings.fill_in_defaults();
+        assert_eq!(bindings.keyboard[&Control::DebugOverlay], KeyCode::F3);
+        assert_eq!(bindings.gamepad[&Control::Back], GamepadButtonType::East);
+        // the default's taken, so it's left unbound
+        assert_eq!(bindings.keyboard.get(&Control::StepPhysics), None);
+    }
+
+    #[test]
+    fn game_and_menu_controls_can_share_a_key() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.bind_key(Control::Confirm, KeyCode::X), Ok(()));
+        assert_eq!(bindings.bind_key(Control::Jump, KeyCode::X), Ok(()));
+        assert_eq!(bindings.keyboard[&Control::Jump], KeyCode::X);
+        assert_eq!(bindings.keyboard[&Control::Confirm], KeyCode::X);
+    }
+}
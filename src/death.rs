@@ -0,0 +1,136 @@
+#![allow(clippy::type_complexity)]
+
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
+use iyes_loopless::state::NextState;
+
+use crate::guy::{Guy, RespawnGuy, GUY_SIZE};
+use crate::level::{Hazard, LevelBounds};
+use crate::lives::Lives;
+use crate::platformer::{Aabb, AppState};
+
+// What's happening within AppState::InGame. Gameplay systems only run while
+// Playing, so the world freezes while the guy's death animation plays.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Resource)]
+pub enum PlayState {
+    Playing,
+    Dying,
+}
+
+pub struct PlayerDied;
+
+// How many times the guy has died in the current level. Reset whenever a level
+// is spawned
+#[derive(Resource, Default)]
+pub struct DeathCount(pub u32);
+
+// Exists from the moment the guy is killed until he respawns. The change to
+// PlayState::Dying only happens at the end of the frame, so there may be more
+// physics steps before then, and this makes sure he only dies once.
+#[derive(Resource)]
+pub struct DyingTimer(Timer);
+
+impl DyingTimer {
+    const DYING_DURATION: f32 = 0.6;
+}
+
+// Starts the death flow. Anything that can kill the guy should go through here,
+// and check that there isn't a DyingTimer first
+pub fn kill_guy(commands: &mut Commands, player_died: &mut EventWriter<PlayerDied>) {
+    debug!("Guy died");
+    player_died.send(PlayerDied);
+    commands.insert_resource(DyingTimer(Timer::from_seconds(
+        DyingTimer::DYING_DURATION,
+        TimerMode::Once,
+    )));
+    commands.insert_resource(NextState(PlayState::Dying));
+}
+
+pub fn hazard_system(
+    guy_query: Query<(&Transform, &Aabb), With<Guy>>,
+    hazard_query: Query<(&Transform, &Aabb), (With<Hazard>, Without<Guy>)>,
+    dying_timer: Option<Res<DyingTimer>>,
+    mut player_died: EventWriter<PlayerDied>,
+    mut commands: Commands,
+) {
+    if dying_timer.is_some() {
+        return;
+    }
+    let Ok((guy_transform, guy_aabb)) = guy_query.get_single() else {
+        return;
+    };
+    let guy_size = guy_aabb.get_scale(guy_transform);
+
+    let touching_hazard =
+        hazard_query.iter().any(|(hazard_transform, hazard_aabb)| {
+            collide(
+                hazard_transform.translation,
+                hazard_aabb.get_scale(hazard_transform),
+                guy_transform.translation,
+                guy_size,
+            )
+            .is_some()
+        });
+    if touching_hazard {
+        kill_guy(&mut commands, &mut player_died);
+    }
+}
+
+// Falling out of the bottom of the level counts as dying
+pub fn kill_plane_system(
+    guy_query: Query<&Transform, With<Guy>>,
+    bounds: Res<LevelBounds>,
+    dying_timer: Option<Res<DyingTimer>>,
+    mut player_died: EventWriter<PlayerDied>,
+    mut commands: Commands,
+) {
+    if dying_timer.is_some() {
+        return;
+    }
+    let Ok(guy_transform) = guy_query.get_single() else {
+        return;
+    };
+    if guy_transform.translation.y < bounds.kill_plane() {
+        kill_guy(&mut commands, &mut player_died);
+    }
+}
+
+pub fn count_deaths(
+    mut player_died: EventReader<PlayerDied>,
+    mut death_count: ResMut<DeathCount>,
+) {
+    death_count.0 += player_died.iter().count() as u32;
+}
+
+// The guy spins and shrinks away, then respawns, or it's game over if that
+// was his last life
+pub fn dying_system(
+    time: Res<Time>,
+    mut dying_timer: ResMut<DyingTimer>,
+    lives: Option<Res<Lives>>,
+    mut guy_query: Query<&mut Transform, With<Guy>>,
+    mut respawn: EventWriter<RespawnGuy>,
+    mut commands: Commands,
+) {
+    const SPIN_SPEED: f32 = 20.0;
+
+    let DyingTimer(timer) = &mut *dying_timer;
+    timer.tick(time.delta());
+    if let Ok(mut transform) = guy_query.get_single_mut() {
+        transform.rotate_z(SPIN_SPEED * time.delta_seconds());
+        transform.scale = GUY_SIZE.extend(0.0) * timer.percent_left();
+    }
+
+    if timer.just_finished() {
+        commands.insert_resource(NextState(PlayState::Playing));
+        if matches!(lives.as_deref(), Some(Lives(0))) {
+            commands.insert_resource(NextState(AppState::GameOver));
+        } else {
+            respawn.send(RespawnGuy);
+        }
+    }
+}
+
+pub fn exit_dying(mut commands: Commands) {
+    commands.remove_resource::<DyingTimer>();
+}
@@ -0,0 +1,177 @@
+#![allow(clippy::type_complexity)]
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::guy::{Guy, JumpState};
+use crate::input::GlobalAction;
+use crate::physics_object::PhysicsObject;
+use crate::platformer::{draw_rect_colored, Aabb, GameFont, GuyContacts};
+
+// Exists while the debug overlay is shown
+#[derive(Resource, Default)]
+pub struct DebugOverlay {
+    // where the guy was for each of the last TRAIL_LENGTH physics steps, oldest
+    // first
+    trail: VecDeque<Vec2>,
+}
+
+impl DebugOverlay {
+    // in physics steps, so two seconds
+    const TRAIL_LENGTH: usize = 240;
+    // don't join up trail points further apart than this, eg after going
+    // through a portal
+    const MAX_TRAIL_GAP: f32 = 30.0;
+    // how long the velocity arrow is, as the time it would take to get to its
+    // end
+    const VELOCITY_SCALE: f32 = 0.1;
+    const NORMAL_LENGTH: f32 = 8.0;
+    const FONT_SIZE: f32 = 10.0;
+    // from the guy's top right corner
+    const TEXT_OFFSET: Vec2 = Vec2::new(4.0, 0.0);
+}
+
+// The guy's jump timers and so on, next to him
+#[derive(Component)]
+pub struct DebugText;
+
+pub fn toggle_debug_overlay_system(
+    global_action_state: Res<ActionState<GlobalAction>>,
+    overlay: Option<Res<DebugOverlay>>,
+    text_query: Query<Entity, With<DebugText>>,
+    font: Res<GameFont>,
+    mut commands: Commands,
+) {
+    if !global_action_state.just_pressed(GlobalAction::DebugOverlay) {
+        return;
+    }
+    if overlay.is_some() {
+        commands.remove_resource::<DebugOverlay>();
+        for text in text_query.iter() {
+            commands.entity(text).despawn_recursive();
+        }
+        return;
+    }
+
+    commands.init_resource::<DebugOverlay>();
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: DebugOverlay::FONT_SIZE,
+                    color: Color::BLACK,
+                },
+            )
+            .with_alignment(TextAlignment::TOP_LEFT),
+            ..default()
+        })
+        .insert(DebugText);
+}
+
+// Runs every physics step, so the trail shows exactly where the guy was on each
+pub fn debug_trail_system(
+    mut overlay: ResMut<DebugOverlay>,
+    guy_query: Query<&Transform, With<Guy>>,
+) {
+    let Ok(guy_transform) = guy_query.get_single() else {
+        return;
+    };
+    if overlay.trail.len() == DebugOverlay::TRAIL_LENGTH {
+        overlay.trail.pop_front();
+    }
+    overlay
+        .trail
+        .push_back(guy_transform.translation.truncate());
+}
+
+// So the trail doesn't carry over from the last life or level
+pub fn clear_debug_trail(overlay: Option<ResMut<DebugOverlay>>) {
+    if let Some(mut overlay) = overlay {
+        overlay.trail.clear();
+    }
+}
+
+pub fn draw_debug_overlay(
+    mut lines: ResMut<DebugLines>,
+    overlay: Res<DebugOverlay>,
+    contacts: Res<GuyContacts>,
+    guy_query: Query<(&Transform, &Aabb, &PhysicsObject, &JumpState), With<Guy>>,
+    mut text_query: Query<
+        (&mut Text, &mut Transform, &mut Visibility),
+        (With<DebugText>, Without<Guy>),
+    >,
+) {
+    // trail
+    for (from, to) in overlay.trail.iter().zip(overlay.trail.iter().skip(1)) {
+        if from.distance(*to) < DebugOverlay::MAX_TRAIL_GAP {
+            lines.line_colored(from.extend(0.0), to.extend(0.0), 0.0, Color::GRAY);
+        }
+    }
+
+    let Ok((mut text, mut text_transform, mut visibility)) =
+        text_query.get_single_mut()
+    else {
+        return;
+    };
+    let Ok((guy_transform, guy_aabb, physics, jump_state)) = guy_query.get_single()
+    else {
+        visibility.is_visible = false;
+        return;
+    };
+    visibility.is_visible = true;
+    let rect = guy_aabb.get_rect(guy_transform);
+    let centre = guy_transform.translation.truncate();
+
+    // the guy's own AABB is drawn by draw_aabbs, but highlight it when he's on
+    // the ground
+    if jump_state.on_ground.is_some() {
+        draw_rect_colored(&mut lines, rect, 0.0, Color::BLUE);
+    }
+
+    // velocity
+    lines.line_colored(
+        centre.extend(0.0),
+        (centre + physics.velocity * DebugOverlay::VELOCITY_SCALE).extend(0.0),
+        0.0,
+        Color::YELLOW,
+    );
+
+    // collision normals
+    for (point, normal) in contacts.0.iter() {
+        lines.line_colored(
+            point.extend(0.0),
+            (*point + *normal * DebugOverlay::NORMAL_LENGTH).extend(0.0),
+            0.0,
+            Color::RED,
+        );
+    }
+
+    let coyote = match &jump_state.coyote_timer.timer {
+        None => "jumped".to_string(),
+        Some(timer) if timer.finished() => "-".to_string(),
+        Some(timer) => format!("{:.2}", timer.remaining_secs()),
+    };
+    let pre_jump_timer = &jump_state.pre_jump_timer.timer;
+    let pre_jump = if pre_jump_timer.finished() {
+        "-".to_string()
+    } else {
+        format!("{:.2}", pre_jump_timer.remaining_secs())
+    };
+    let on_ground = match jump_state.on_ground {
+        Some(_) => "yes",
+        None => "no",
+    };
+    let value = format!(
+        "ground {on_ground}\ncoyote {coyote}\npre-jump {pre_jump}\nvel {:.0}, {:.0}",
+        physics.velocity.x, physics.velocity.y,
+    );
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+    text_transform.translation = (rect.max + DebugOverlay::TEXT_OFFSET).extend(10.0);
+}
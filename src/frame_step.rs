@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::input::GlobalAction;
+use crate::platformer::{AppState, PHYSICS_TIME_STEP, TIME_STEP};
+
+// Exists while the game is being advanced a step at a time, which is started
+// from the pause menu. The clock is paused in the meantime, so the fixed
+// timesteps only run when we push it forwards.
+#[derive(Resource, Default, Debug)]
+pub struct FrameStepping {
+    // how far to push the clock forwards at the start of the next frame
+    pending: Duration,
+}
+
+// Step physics advances by exactly one physics step. Step input advances by one
+// input step, which is a couple of physics steps. Either way the input and
+// physics timesteps keep the same ratio as at full speed.
+pub fn frame_step_input_system(
+    global_action_state: Res<ActionState<GlobalAction>>,
+    state: Res<CurrentState<AppState>>,
+    frame_stepping: Option<ResMut<FrameStepping>>,
+    mut time: ResMut<Time>,
+    mut commands: Commands,
+) {
+    let step = if global_action_state.just_pressed(GlobalAction::StepPhysics) {
+        Some(Duration::from_secs_f32(PHYSICS_TIME_STEP))
+    } else if global_action_state.just_pressed(GlobalAction::StepInput) {
+        Some(Duration::from_secs_f32(TIME_STEP))
+    } else {
+        None
+    };
+
+    match (state.0, frame_stepping) {
+        (AppState::Paused, None) => {
+            let Some(step) = step else {
+                return;
+            };
+            time.pause();
+            commands.insert_resource(FrameStepping { pending: step });
+            commands.insert_resource(NextState(AppState::InGame));
+        }
+        (AppState::InGame, Some(mut frame_stepping)) => {
+            // global_input_system only runs when we step, so watch for start
+            // here instead
+            if global_action_state.just_pressed(GlobalAction::Start) {
+                commands.insert_resource(NextState(AppState::Paused));
+                return;
+            }
+            if let Some(step) = step {
+                frame_stepping.pending += step;
+            }
+        }
+        _ => (),
+    }
+}
+
+// Runs straight after bevy updates the clock, so that the fixed timesteps see
+// exactly the time we've stepped by. Waits until we're actually in game, since
+// the state only changes after the fixed timesteps have run.
+pub fn frame_step_clock_system(
+    state: Res<CurrentState<AppState>>,
+    mut frame_stepping: ResMut<FrameStepping>,
+    mut time: ResMut<Time>,
+) {
+    if state.0 != AppState::InGame || frame_stepping.pending.is_zero() {
+        return;
+    }
+    let Some(last_update) = time.last_update() else {
+        return;
+    };
+    time.unpause();
+    time.update_with_instant(last_update + frame_stepping.pending);
+    time.pause();
+    frame_stepping.pending = Duration::ZERO;
+}
+
+// However we leave the game, eg back to the pause menu or on reaching the goal,
+// go back to full speed
+pub fn stop_frame_stepping(mut time: ResMut<Time>, mut commands: Commands) {
+    time.unpause();
+    commands.remove_resource::<FrameStepping>();
+}
@@ -1,3 +1,4 @@
+use crate::bindings::Bindings;
 use crate::input::{make_game_input_map, GameAction};
 use crate::physics_object::{Gravity, PhysicsObject};
 use crate::platformer::Aabb;
@@ -114,7 +115,6 @@ impl JumpState {
         physics: &mut PhysicsObject,
         guy_transform: &mut Transform,
     ) {
-        const JUMP_SPEED: f32 = 600.0;
         physics.velocity.y = JUMP_SPEED;
         guy_transform.scale = GUY_JUMPING_SIZE;
         self.on_ground = None;
@@ -151,11 +151,13 @@ pub struct GuyBundle {
 
 pub const GUY_SIZE: Vec2 = Vec2::new(16.0, 16.0);
 pub const GUY_JUMPING_SIZE: Vec3 = Vec3::new(14.0, 20.0, 0.0);
+pub const GUY_H_SPEED: f32 = 180.;
+pub const JUMP_SPEED: f32 = 600.0;
 
 impl Default for GuyBundle {
     fn default() -> Self {
         GuyBundle {
-            guy: Guy { h_speed: 180. },
+            guy: Guy { h_speed: GUY_H_SPEED },
             sprite: SpriteBundle {
                 transform: Transform {
                     scale: GUY_SIZE.extend(0.0),
@@ -173,19 +175,23 @@ impl Default for GuyBundle {
             gravity: Gravity::default(),
             input_manager: InputManagerBundle {
                 action_state: ActionState::default(),
-                input_map: make_game_input_map(),
+                input_map: make_game_input_map(&Bindings::default()),
             },
         }
     }
 }
 
 impl GuyBundle {
-    pub fn with_translation(translation: Vec3) -> Self {
+    pub fn with_translation(translation: Vec3, bindings: &Bindings) -> Self {
         let mut guy = GuyBundle::default();
         guy.sprite.transform.translation = translation;
+        guy.input_manager.input_map = make_game_input_map(bindings);
         guy
     }
 }
 
 #[derive(Component)]
 pub struct CanFly;
+
+// Sent to put the guy back at the RespawnPoint
+pub struct RespawnGuy;
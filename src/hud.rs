@@ -0,0 +1,202 @@
+#![allow(clippy::type_complexity)]
+
+use bevy::prelude::*;
+
+use crate::death::DeathCount;
+use crate::level::{CollectibleTally, LoadingLevel};
+use crate::level_meta::CurrentLevelMeta;
+use crate::lives::Lives;
+use crate::platformer::{BASE_HEIGHT, BASE_WIDTH};
+use crate::speedrun::{
+    format_delta, format_time, LevelTimer, PersonalBest, SpeedrunConfig,
+};
+
+// The root of the HUD, only shown while AppState::InGame
+#[derive(Component)]
+pub struct Hud;
+
+// Which piece of information a HUD text shows. Each is kept up to date from a
+// resource by hud_system.
+#[derive(Component, Clone, Copy, Debug)]
+pub enum HudText {
+    LevelName,
+    Time,
+    // the last checkpoint split, compared against the personal best
+    Split,
+    Deaths,
+    Collectibles,
+    Lives,
+}
+
+const HUD_FONT_SIZE: f32 = 16.0;
+const HUD_MARGIN: f32 = 8.0;
+
+pub fn spawn_hud(commands: &mut Commands, font: &Handle<Font>) {
+    let style = TextStyle {
+        font: font.clone(),
+        font_size: HUD_FONT_SIZE,
+        color: Color::BLACK,
+    };
+    // ColumnReverse lays children out from the top down
+    let column = |align_items| NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items,
+            ..default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::SpaceBetween,
+                // UI y points up, so this keeps the columns at the top of the
+                // screen
+                align_items: AlignItems::FlexEnd,
+                padding: UiRect::all(Val::Px(HUD_MARGIN)),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Hud)
+        .insert(Visibility { is_visible: false })
+        .with_children(|parent| {
+            parent
+                .spawn(column(AlignItems::FlexStart))
+                .with_children(|left| {
+                    for hud_text in
+                        [HudText::LevelName, HudText::Time, HudText::Split]
+                    {
+                        left.spawn(TextBundle::from_section("", style.clone()))
+                            .insert(hud_text);
+                    }
+                });
+            parent
+                .spawn(column(AlignItems::FlexEnd))
+                .with_children(|right| {
+                    for hud_text in
+                        [HudText::Deaths, HudText::Collectibles, HudText::Lives]
+                    {
+                        right
+                            .spawn(TextBundle::from_section("", style.clone()))
+                            .insert(hud_text);
+                    }
+                });
+        });
+}
+
+pub fn show_hud(mut hud_query: Query<&mut Visibility, With<Hud>>) {
+    for mut visibility in hud_query.iter_mut() {
+        visibility.is_visible = true;
+    }
+}
+
+pub fn hide_hud(mut hud_query: Query<&mut Visibility, With<Hud>>) {
+    for mut visibility in hud_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+// Only rewrites the texts whose resources have changed, apart from the time
+#[allow(clippy::too_many_arguments)]
+pub fn hud_system(
+    level_meta: Option<Res<CurrentLevelMeta>>,
+    loading_level: Option<Res<LoadingLevel>>,
+    level_timer: Res<LevelTimer>,
+    personal_best: Option<Res<PersonalBest>>,
+    speedrun_config: Res<SpeedrunConfig>,
+    deaths: Res<DeathCount>,
+    tally: Res<CollectibleTally>,
+    lives: Option<Res<Lives>>,
+    mut text_query: Query<(&HudText, &mut Text, &mut Visibility)>,
+) {
+    for (hud_text, mut text, mut visibility) in text_query.iter_mut() {
+        let value = match hud_text {
+            HudText::LevelName => {
+                let (Some(meta), Some(loading_level)) =
+                    (&level_meta, &loading_level)
+                else {
+                    continue;
+                };
+                if !meta.is_changed() && !loading_level.is_changed() {
+                    continue;
+                }
+                let CurrentLevelMeta(meta) = &**meta;
+                match &**loading_level {
+                    LoadingLevel::Path(level_path) => meta.display_name(level_path),
+                    LoadingLevel::Overworld => meta.name.clone().unwrap_or_default(),
+                }
+            }
+            HudText::Time => format_time(level_timer.steps),
+            // only shown once a checkpoint has been reached
+            HudText::Split => {
+                let last_split = level_timer
+                    .splits
+                    .last()
+                    .filter(|_| speedrun_config.show_splits);
+                if visibility.is_visible != last_split.is_some() {
+                    visibility.is_visible = last_split.is_some();
+                }
+                let Some(split) = last_split else {
+                    continue;
+                };
+                let number = level_timer.splits.len();
+                let best_split = personal_best
+                    .as_deref()
+                    .and_then(|best| best.split_at(split.checkpoint));
+                match best_split {
+                    Some(best) => format!(
+                        "split {number} {} ({})",
+                        format_time(split.steps),
+                        format_delta(split.steps, best)
+                    ),
+                    None => format!("split {number} {}", format_time(split.steps)),
+                }
+            }
+            HudText::Deaths => {
+                if !deaths.is_changed() {
+                    continue;
+                }
+                format!("deaths {}", deaths.0)
+            }
+            // only shown in levels that have collectibles
+            HudText::Collectibles => {
+                if !tally.is_changed() {
+                    continue;
+                }
+                visibility.is_visible = tally.total > 0;
+                format!("coins {}/{}", tally.collected, tally.total)
+            }
+            // only shown when lives are turned on
+            HudText::Lives => {
+                if visibility.is_visible != lives.is_some() {
+                    visibility.is_visible = lives.is_some();
+                }
+                let Some(lives) = lives.as_deref() else {
+                    continue;
+                };
+                format!("lives {}", lives.0)
+            }
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+// Scales the UI along with the window, so it looks the same as it does at the
+// base resolution. Keeps to whichever dimension is relatively smaller so
+// nothing gets cut off.
+pub fn ui_scale_system(windows: Res<Windows>, mut ui_scale: ResMut<UiScale>) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let scale =
+        (window.width() / BASE_WIDTH).min(window.height() / BASE_HEIGHT) as f64;
+    if ui_scale.scale != scale {
+        ui_scale.scale = scale;
+    }
+}
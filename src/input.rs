@@ -1,19 +1,32 @@
 #![allow(clippy::type_complexity)]
 
+use std::collections::HashMap;
+
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::*;
 
+use crate::bindings::{Bindings, Control};
 use crate::guy::*;
-use crate::level::LoadingLevel;
 use crate::physics_object::{Gravity, PhysicsObject};
 use crate::platformer::AppState;
+use crate::state_transitions::return_to_overworld;
 
 #[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum GlobalAction {
     Start,
     Select,
+    // menu navigation
+    Up,
+    Down,
+    Confirm,
+    Back,
+    // frame-stepping while paused
+    StepPhysics,
+    StepInput,
+    // show the debug overlay, see debug_overlay.rs
+    DebugOverlay,
 }
 
 #[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash)]
@@ -21,40 +34,115 @@ pub enum GameAction {
     Move,
     Jump,
     Debug,
+    // go back to the last checkpoint
+    Restart,
 }
 
-pub fn make_global_input_map() -> InputMap<GlobalAction> {
+// how far the stick has to be pushed to move through a menu
+const MENU_STICK_THRESHOLD: f32 = 0.5;
+
+pub fn make_global_input_map(bindings: &Bindings) -> InputMap<GlobalAction> {
     let mut input_map = InputMap::default();
-    // keyboard
-    input_map.insert_multiple([(KeyCode::Return, GlobalAction::Start)]);
+    insert_bindings(
+        &mut input_map,
+        bindings,
+        [
+            (Control::Start, GlobalAction::Start),
+            (Control::Select, GlobalAction::Select),
+            (Control::MenuUp, GlobalAction::Up),
+            (Control::MenuDown, GlobalAction::Down),
+            (Control::Confirm, GlobalAction::Confirm),
+            (Control::Back, GlobalAction::Back),
+            (Control::StepPhysics, GlobalAction::StepPhysics),
+            (Control::StepInput, GlobalAction::StepInput),
+            (Control::DebugOverlay, GlobalAction::DebugOverlay),
+        ],
+    );
 
-    // gamepad
-    input_map.insert_multiple([
-        (GamepadButtonType::Start, GlobalAction::Start),
-        (GamepadButtonType::Select, GlobalAction::Select),
-    ]);
+    // the stick isn't rebindable
+    input_map.insert(
+        SingleAxis::positive_only(GamepadAxisType::LeftStickY, MENU_STICK_THRESHOLD),
+        GlobalAction::Up,
+    );
+    input_map.insert(
+        SingleAxis::negative_only(
+            GamepadAxisType::LeftStickY,
+            -MENU_STICK_THRESHOLD,
+        ),
+        GlobalAction::Down,
+    );
     input_map
 }
 
-pub fn make_game_input_map() -> InputMap<GameAction> {
+pub fn make_game_input_map(bindings: &Bindings) -> InputMap<GameAction> {
     let mut input_map = InputMap::default();
+    insert_bindings(
+        &mut input_map,
+        bindings,
+        [
+            (Control::Jump, GameAction::Jump),
+            (Control::Debug, GameAction::Debug),
+            (Control::Restart, GameAction::Restart),
+        ],
+    );
+
     // keyboard
-    input_map.insert_multiple([
-        (KeyCode::Grave, GameAction::Debug),
-        (KeyCode::Space, GameAction::Jump),
-    ]);
-    input_map.insert(VirtualDPad::arrow_keys(), GameAction::Move);
+    if let Some(dpad) = move_dpad(&bindings.keyboard) {
+        input_map.insert(dpad, GameAction::Move);
+    }
 
     // gamepad
-    input_map.insert_multiple([
-        (GamepadButtonType::North, GameAction::Debug),
-        (GamepadButtonType::South, GameAction::Jump),
-    ]);
-    input_map.insert(VirtualDPad::dpad(), GameAction::Move);
+    if let Some(dpad) = move_dpad(&bindings.gamepad) {
+        input_map.insert(dpad, GameAction::Move);
+    }
+    // the stick isn't rebindable
     input_map.insert(DualAxis::left_stick(), GameAction::Move);
     input_map
 }
 
+// Adds the key and gamepad button bound to each control
+fn insert_bindings<A: Actionlike, const N: usize>(
+    input_map: &mut InputMap<A>,
+    bindings: &Bindings,
+    controls: [(Control, A); N],
+) {
+    for (control, action) in controls {
+        if let Some(key) = bindings.keyboard.get(&control) {
+            input_map.insert(*key, action.clone());
+        }
+        if let Some(button) = bindings.gamepad.get(&control) {
+            input_map.insert(*button, action);
+        }
+    }
+}
+
+// Movement only works with all four directions bound
+fn move_dpad<T: Copy + Into<InputKind>>(
+    bound: &HashMap<Control, T>,
+) -> Option<VirtualDPad> {
+    Some(VirtualDPad {
+        up: (*bound.get(&Control::MoveUp)?).into(),
+        down: (*bound.get(&Control::MoveDown)?).into(),
+        left: (*bound.get(&Control::MoveLeft)?).into(),
+        right: (*bound.get(&Control::MoveRight)?).into(),
+    })
+}
+
+// Puts changed bindings into effect, for both the global actions and the guy
+pub fn apply_bindings_system(
+    bindings: Res<Bindings>,
+    mut global_input_map: ResMut<InputMap<GlobalAction>>,
+    mut game_input_maps: Query<&mut InputMap<GameAction>>,
+) {
+    if !bindings.is_changed() {
+        return;
+    }
+    *global_input_map = make_global_input_map(&bindings);
+    for mut input_map in game_input_maps.iter_mut() {
+        *input_map = make_game_input_map(&bindings);
+    }
+}
+
 pub fn global_input_system(
     global_action_state: Res<ActionState<GlobalAction>>,
     mut commands: Commands,
@@ -73,15 +161,11 @@ pub fn global_input_system(
         use AppState::*;
         match state.0 {
             Loading => (),
-            MainMenu => {
-                // I don't really love this approach of needing to insert a LoadingLevel
-                // when I switch to the loading state
-                // not sure if there's a better way to communicate between states.
-                commands.insert_resource(LoadingLevel::Overworld);
-                commands.insert_resource(NextState(AppState::Loading))
-            }
+            // these menus treat start as confirming their selection
+            MainMenu | LevelSelect | GameOver | Settings => (),
             InGame => commands.insert_resource(NextState(AppState::Paused)),
             Paused => commands.insert_resource(NextState(AppState::InGame)),
+            LevelComplete => return_to_overworld(&mut commands),
         };
     }
 }
@@ -96,6 +180,7 @@ pub fn game_input_system(
         &mut JumpState,
         Option<&CanFly>,
     )>,
+    mut respawn: EventWriter<RespawnGuy>,
     mut commands: Commands,
 ) {
     let (
@@ -139,4 +224,8 @@ pub fn game_input_system(
     if action_state.just_pressed(GameAction::Jump) {
         jump_state.try_jump(&mut physics, &mut transform);
     }
+
+    if action_state.just_pressed(GameAction::Restart) {
+        respawn.send(RespawnGuy);
+    }
 }
@@ -1,26 +1,156 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use crate::platformer::{Aabb, DrawAabb};
-use bevy::{prelude::*, render::render_resource::TextureFormat};
+use crate::level_meta::LevelMeta;
+use crate::level_pack::LevelPack;
+use crate::platformer::{tile_to_world, Aabb, DrawAabb};
+use crate::save::{LevelProgress, SaveData};
+use crate::speedrun::{format_time, Split};
+use bevy::{math::Rect, prelude::*, render::render_resource::TextureFormat};
+use serde::Deserialize;
 
 use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
 
 pub enum LevelContents {
     Player,
     Tile,
-    Portal(PathBuf),
+    // name is shown above the portal, if there is one. Locked portals can't be
+    // used
+    Portal {
+        target: PortalTarget,
+        name: Option<String>,
+        locked: bool,
+    },
+    // somewhere the guy can be sent to by a portal, rather than starting at the
+    // Player position
+    SpawnPoint(String),
+    // reaching this completes the level
+    Goal,
+    // a coin that's counted when the guy touches it
+    Collectible,
+    // touching one makes it where the guy respawns
+    Checkpoint,
+    // gives the guy another life, if lives are turned on
+    ExtraLife,
+    // touching one kills the guy
+    Hazard(HazardKind),
+    // a wall that opens once every level listed (by id) has been completed
+    Gate {
+        requires: Vec<String>,
+    },
 }
 
-// Vec2 is the position in units of 18x18 tiles, not in world space
-pub struct Level(pub HashMap<IVec2, LevelContents>);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HazardKind {
+    Spikes,
+    Lava,
+}
+
+// Where a portal sends the guy
+#[derive(Deserialize, Clone, Debug)]
+pub enum PortalTarget {
+    // Another level, starting either at its usual Player position or at the
+    // named spawn point
+    Level {
+        path: PathBuf,
+        #[serde(default)]
+        spawn: Option<String>,
+    },
+    // A named spawn point in the current level
+    Spawn(String),
+    // A level from the level pack, by its id. Resolved into `Level` when the
+    // level is loaded, so that portals can stay valid if levels are renamed or
+    // reordered
+    LevelId(String),
+}
+
+// Which layer a decoration is drawn in, relative to the level contents
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecorationLayer {
+    Background,
+    Foreground,
+}
+
+impl DecorationLayer {
+    pub const ALL: [DecorationLayer; 2] =
+        [DecorationLayer::Background, DecorationLayer::Foreground];
+
+    // The level contents themselves are drawn at LEVEL_CONTENTS_Z, between the
+    // two layers
+    pub fn z(self) -> f32 {
+        match self {
+            DecorationLayer::Background => LEVEL_CONTENTS_Z - 1.0,
+            DecorationLayer::Foreground => LEVEL_CONTENTS_Z + 1.0,
+        }
+    }
+
+    // The image for a layer sits alongside the level image, eg `level1.bg.png`
+    // for `level1.png`
+    pub fn image_path(self, level_path: &Path) -> PathBuf {
+        level_path.with_extension(match self {
+            DecorationLayer::Background => "bg.png",
+            DecorationLayer::Foreground => "fg.png",
+        })
+    }
+}
+
+pub const LEVEL_CONTENTS_Z: f32 = 1.0;
+
+// Render-only tiles, which the guy doesn't collide with
+pub struct Decoration {
+    pub tile_index: usize,
+    pub layer: DecorationLayer,
+}
+
+// Colours that represent decorations, the tile in the tileset to draw for each,
+// and the layer it goes in when it's part of the main level image. In a separate
+// layer image every decoration goes in that image's layer.
+const DECORATION_PALETTE: [(Rgba<u8>, usize, DecorationLayer); 4] = [
+    // background wall, in brick so it doesn't look like something to stand on
+    (Rgba([96, 96, 96, 255]), 6, DecorationLayer::Background),
+    // sign
+    (Rgba([128, 64, 0, 255]), 86, DecorationLayer::Background),
+    // grass
+    (Rgba([0, 128, 0, 255]), 124, DecorationLayer::Foreground),
+    // looks like a solid tile, for hiding secret passages
+    (Rgba([64, 64, 64, 255]), 122, DecorationLayer::Foreground),
+];
+
+fn decoration_for_pixel(pixel: &Rgba<u8>) -> Option<(usize, DecorationLayer)> {
+    DECORATION_PALETTE
+        .iter()
+        .find(|(colour, _, _)| colour == pixel)
+        .map(|&(_, tile_index, layer)| (tile_index, layer))
+}
+
+// IVec2 is the position in units of 18x18 tiles, not in world space
+pub struct Level(
+    pub HashMap<IVec2, LevelContents>,
+    pub Vec<(IVec2, Decoration)>,
+);
 
 #[derive(Debug)]
 pub enum LevelParseError {
     WrongNumberPlayers(i32),
+    // a portal pixel's index isn't in the level metadata's list of portals
+    UnknownPortal(u8),
+    // a spawn point pixel's index isn't in the level metadata's list of spawn
+    // points
+    UnknownSpawnPoint(u8),
+    // a portal leads to a spawn point that isn't in the level
+    MissingSpawnPoint(String),
+    // a gate pixel's index isn't in the level metadata's list of gates
+    UnknownGate(u8),
+    // a portal leads to a level id that isn't in the level pack
+    UnknownLevelId(String),
+    // the image isn't 8-bit RGBA, eg it was saved as greyscale
+    UnsupportedImageFormat(TextureFormat),
 }
 
 // Specifies a level to be either fetched or generated
-#[derive(Resource)]
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
 pub enum LoadingLevel {
     Path(PathBuf),
     // needs to be its own variant because menu is dynamically generated
@@ -28,9 +158,64 @@ pub enum LoadingLevel {
     Overworld,
 }
 
+// Inserted alongside LoadingLevel when the guy should start the level at a named
+// spawn point rather than the usual player position
+#[derive(Resource)]
+pub struct TargetSpawnPoint(pub String);
+
+// The level whose portal we last went through from the overworld, so that we can
+// put the guy back next to it when he returns
+#[derive(Resource)]
+pub struct ReturnPortal(pub PathBuf);
+
+pub struct LevelCompleted {
+    pub level_path: PathBuf,
+    // in physics steps
+    pub steps: u32,
+    pub splits: Vec<Split>,
+    pub collectibles: CollectibleTally,
+}
+
+// The area the current level takes up in world space, so that the camera can
+// stay inside it and we can tell when things have fallen out of it
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LevelBounds(pub Rect);
+
+impl LevelBounds {
+    // how far below the bottom of the level something has to fall before it's
+    // gone for good
+    const KILL_PLANE_DEPTH: f32 = 200.0;
+
+    pub fn kill_plane(&self) -> f32 {
+        self.0.min.y - Self::KILL_PLANE_DEPTH
+    }
+}
+
+// Where the guy is put back when he dies or restarts. Starts where he's spawned,
+// and moves to each checkpoint he touches
+#[derive(Resource)]
+pub struct RespawnPoint(pub Vec3);
+
+// How many of the current level's collectibles have been picked up. Reset
+// whenever a level is spawned
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct CollectibleTally {
+    pub collected: u32,
+    pub total: u32,
+}
+
 // Copied from
 // https://github.com/bevyengine/bevy/blob/v0.8.1/crates/bevy_render/src/texture/image_texture_conversion.rs
 // Temporary measure for 0.8, 0.9 will have a public function
+fn bevy_image_to_rgba(img: &Image) -> Result<RgbaImage, LevelParseError> {
+    match texture_to_image(img) {
+        Some(DynamicImage::ImageRgba8(rgba)) => Ok(rgba),
+        _ => Err(LevelParseError::UnsupportedImageFormat(
+            img.texture_descriptor.format,
+        )),
+    }
+}
+
 fn texture_to_image(texture: &Image) -> Option<DynamicImage> {
     match texture.texture_descriptor.format {
         TextureFormat::R8Unorm => ImageBuffer::from_raw(
@@ -56,13 +241,35 @@ fn texture_to_image(texture: &Image) -> Option<DynamicImage> {
 }
 
 impl Level {
-    pub fn from_bevy_image(img: &Image) -> Result<Self, LevelParseError> {
-        let dynamic_image = texture_to_image(img).unwrap();
-        let rgba: &RgbaImage = dynamic_image
-            .as_rgba8()
-            .expect("level could not be converted to rgba8");
+    pub fn from_bevy_image(
+        img: &Image,
+        meta: &LevelMeta,
+    ) -> Result<Self, LevelParseError> {
+        Level::from_rgba(&bevy_image_to_rgba(img)?, meta)
+    }
+
+    pub fn add_decoration_layer_from_bevy_image(
+        &mut self,
+        img: &Image,
+        layer: DecorationLayer,
+    ) -> Result<(), LevelParseError> {
+        self.add_decoration_layer(&bevy_image_to_rgba(img)?, layer);
+        Ok(())
+    }
 
-        Level::from_rgba(rgba)
+    pub fn add_decoration_layer(
+        &mut self,
+        layer_image: &RgbaImage,
+        layer: DecorationLayer,
+    ) {
+        self.1
+            .extend(layer_image.enumerate_pixels().filter_map(|(x, y, pixel)| {
+                let (tile_index, _) = decoration_for_pixel(pixel)?;
+                Some((
+                    IVec2::new(x as i32, y as i32),
+                    Decoration { tile_index, layer },
+                ))
+            }));
     }
 
     // pub fn load(to_load: &LoadingLevel) -> Self {
@@ -91,56 +298,258 @@ impl Level {
     //     }
     // }
 
-    pub fn from_rgba(level_image: &RgbaImage) -> Result<Level, LevelParseError> {
+    // Portals, spawn points and gates carry more information than fits in a
+    // pixel, so their pixels hold an index in the green channel into lists in the
+    // level's metadata
+    pub fn from_rgba(
+        level_image: &RgbaImage,
+        meta: &LevelMeta,
+    ) -> Result<Level, LevelParseError> {
         const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
         const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+        const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+        const YELLOW: Rgba<u8> = Rgba([255, 255, 0, 255]);
+        const CYAN: Rgba<u8> = Rgba([0, 255, 255, 255]);
+        const LIGHT_GREY: Rgba<u8> = Rgba([192, 192, 192, 255]);
+        const ORANGE: Rgba<u8> = Rgba([255, 128, 0, 255]);
+        const PINK: Rgba<u8> = Rgba([255, 0, 128, 255]);
 
         let mut player_count = 0;
+        let mut contents = HashMap::new();
+        let mut decorations = Vec::new();
+
+        for (x, y, pixel) in level_image.enumerate_pixels() {
+            let position = IVec2::new(x as i32, y as i32);
+            let level_contents = match *pixel {
+                // Black represents a wall tile
+                BLACK => LevelContents::Tile,
+
+                // red represents the player
+                RED => {
+                    player_count += 1;
+                    LevelContents::Player
+                }
+
+                // green represents the goal
+                GREEN => LevelContents::Goal,
+
+                // yellow represents a collectible
+                YELLOW => LevelContents::Collectible,
+
+                // cyan represents a checkpoint
+                CYAN => LevelContents::Checkpoint,
+
+                // pink represents an extra life
+                PINK => LevelContents::ExtraLife,
+
+                // light grey represents spikes
+                LIGHT_GREY => LevelContents::Hazard(HazardKind::Spikes),
 
-        let level: Level = Level(
-            level_image
-                .enumerate_pixels()
-                .filter_map(|(x, y, pixel)| {
-                    match *pixel {
-                        // Black represents a wall tile
-                        BLACK => Some((
-                            IVec2::new(x as i32, y as i32),
-                            LevelContents::Tile,
-                        )),
-
-                        // red represents the player
-                        RED => {
-                            player_count += 1;
-                            Some((
-                                IVec2::new(x as i32, y as i32),
-                                LevelContents::Player,
-                            ))
-                        }
-                        _ => None,
+                // orange represents lava
+                ORANGE => LevelContents::Hazard(HazardKind::Lava),
+
+                // blue represents a portal
+                Rgba([0, index, 255, 255]) => {
+                    let target = meta
+                        .portals
+                        .get(index as usize)
+                        .ok_or(LevelParseError::UnknownPortal(index))?;
+                    LevelContents::Portal {
+                        target: target.clone(),
+                        name: None,
+                        locked: false,
                     }
-                })
-                .collect(),
-        );
+                }
+
+                // magenta represents a spawn point
+                Rgba([255, index, 255, 255]) => {
+                    let name = meta
+                        .spawn_points
+                        .get(index as usize)
+                        .ok_or(LevelParseError::UnknownSpawnPoint(index))?;
+                    LevelContents::SpawnPoint(name.clone())
+                }
+
+                // purple represents a gate
+                Rgba([128, index, 255, 255]) => {
+                    let requires = meta
+                        .gates
+                        .get(index as usize)
+                        .ok_or(LevelParseError::UnknownGate(index))?;
+                    LevelContents::Gate {
+                        requires: requires.clone(),
+                    }
+                }
+
+                _ => {
+                    if let Some((tile_index, layer)) = decoration_for_pixel(pixel) {
+                        decorations
+                            .push((position, Decoration { tile_index, layer }));
+                    }
+                    continue;
+                }
+            };
+            contents.insert(position, level_contents);
+        }
 
         if player_count != 1 {
-            Err(LevelParseError::WrongNumberPlayers(player_count))
+            return Err(LevelParseError::WrongNumberPlayers(player_count));
+        }
+
+        let level = Level(contents, decorations);
+        for level_contents in level.0.values() {
+            if let LevelContents::Portal {
+                target: PortalTarget::Spawn(spawn),
+                ..
+            } = level_contents
+            {
+                if level.spawn_point(spawn).is_none() {
+                    return Err(LevelParseError::MissingSpawnPoint(spawn.clone()));
+                }
+            }
+        }
+
+        Ok(level)
+    }
+
+    pub fn bounds(&self) -> LevelBounds {
+        let positions = self
+            .0
+            .keys()
+            .chain(self.1.iter().map(|(position, _)| position));
+        let (min, max) = positions.fold(
+            (IVec2::splat(i32::MAX), IVec2::splat(i32::MIN)),
+            |(min, max), &position| (min.min(position), max.max(position)),
+        );
+        let (min, max) = if min.cmple(max).all() {
+            (min, max)
         } else {
-            Ok(level)
+            (IVec2::ZERO, IVec2::ZERO)
+        };
+
+        // the level grid's y points down, so its max y is the bottom of the level
+        let half_tile = TileBundle::TILE_SIZE / 2.0;
+        let bottom_left = tile_to_world(IVec2::new(min.x, max.y), 0.0).truncate();
+        let top_right = tile_to_world(IVec2::new(max.x, min.y), 0.0).truncate();
+        LevelBounds(Rect {
+            min: bottom_left - half_tile,
+            max: top_right + half_tile,
+        })
+    }
+
+    pub fn collectible_count(&self) -> u32 {
+        self.0
+            .values()
+            .filter(|level_contents| {
+                matches!(level_contents, LevelContents::Collectible)
+            })
+            .count() as u32
+    }
+
+    pub fn spawn_point(&self, name: &str) -> Option<IVec2> {
+        self.0
+            .iter()
+            .find_map(|(position, level_contents)| match level_contents {
+                LevelContents::SpawnPoint(spawn) if spawn == name => Some(*position),
+                _ => None,
+            })
+    }
+
+    // Turns portals to level ids into portals to the levels' paths, labelled with
+    // the levels' names
+    pub fn resolve_level_ids(
+        &mut self,
+        level_pack: &LevelPack,
+    ) -> Result<(), LevelParseError> {
+        for level_contents in self.0.values_mut() {
+            let LevelContents::Portal { target, name, .. } = level_contents else {
+                continue;
+            };
+            let PortalTarget::LevelId(id) = target else {
+                continue;
+            };
+            let entry = level_pack
+                .entry(id)
+                .ok_or_else(|| LevelParseError::UnknownLevelId(id.clone()))?;
+            *name = Some(entry.name.clone());
+            *target = PortalTarget::Level {
+                path: entry.path.clone(),
+                spawn: None,
+            };
         }
+        Ok(())
     }
 
-    pub fn generate_overworld_level() -> Self {
-        let levels: glob::Paths =
-            glob::glob("assets/level*.png").expect("failed to read glob pattern");
+    // Removes every gate whose required levels have all been completed
+    pub fn open_gates(&mut self, level_pack: &LevelPack, save: &SaveData) {
+        self.0.retain(|_, level_contents| {
+            let LevelContents::Gate { requires } = level_contents else {
+                return true;
+            };
+            !requires.iter().all(|id| {
+                level_pack
+                    .entry(id)
+                    .is_some_and(|entry| save.is_completed(&entry.path))
+            })
+        });
+    }
+
+    // Moves the player next to the portal leading to `level_path`, if there is
+    // one, so that coming back to a level feels like stepping back out of its
+    // portal
+    pub fn place_player_by_portal(&mut self, level_path: &Path) {
+        let portal_position = self.0.iter().find_map(
+            |(position, level_contents)| match level_contents {
+                LevelContents::Portal {
+                    target: PortalTarget::Level { path, .. },
+                    ..
+                } if path == level_path => Some(*position),
+                _ => None,
+            },
+        );
+        let Some(portal_position) = portal_position else {
+            return;
+        };
+        let beside = [IVec2::X, -IVec2::X]
+            .into_iter()
+            .map(|offset| portal_position + offset)
+            .find(|position| !self.0.contains_key(position));
+        let Some(beside) = beside else {
+            return;
+        };
+        self.0.retain(|_, level_contents| {
+            !matches!(level_contents, LevelContents::Player)
+        });
+        self.0.insert(beside, LevelContents::Player);
+    }
+
+    // Used when the level pack doesn't have an authored overworld. One portal per
+    // level in the pack, in order. Each is locked until the level before it has
+    // been completed. If `return_to` is given, the player starts next to the
+    // portal to that level
+    pub fn generate_overworld_level(
+        level_pack: &LevelPack,
+        save: &SaveData,
+        return_to: Option<&Path>,
+    ) -> Self {
+        let levels = &level_pack.levels;
 
         const N_TILES_PER_LEVEL: usize = 5;
+        let player_position = return_to
+            .and_then(|return_to| {
+                levels.iter().position(|level| level.path == return_to)
+            })
+            .map_or(IVec2::new(0, -1), |i| {
+                IVec2::new((i * N_TILES_PER_LEVEL) as i32 + 3, -1)
+            });
+
         Level(
             levels
-                .map(Result::unwrap)
-                .map(|p| p.strip_prefix("assets").unwrap().to_path_buf())
+                .iter()
                 .enumerate()
                 .flat_map(|(i, level)| {
                     let offset = i * N_TILES_PER_LEVEL;
+                    let locked = i > 0 && !save.is_completed(&levels[i - 1].path);
                     (offset..offset + N_TILES_PER_LEVEL)
                         .map(|x| {
                             let vec = IVec2::new(x as i32, 0);
@@ -148,17 +557,42 @@ impl Level {
                         })
                         .chain(std::iter::once((
                             IVec2::new(offset as i32 + 2, -1),
-                            LevelContents::Portal(level),
+                            LevelContents::Portal {
+                                target: PortalTarget::Level {
+                                    path: level.path.clone(),
+                                    spawn: None,
+                                },
+                                name: Some(level.name.clone()),
+                                locked,
+                            },
                         )))
                 })
-                .chain(std::iter::once((IVec2::new(0, -1), LevelContents::Player)))
+                .chain(std::iter::once((player_position, LevelContents::Player)))
                 .collect(),
+            Vec::new(),
         )
     }
 }
 
 #[derive(Component)]
-pub struct Portal(pub PathBuf);
+pub struct Portal(pub PortalTarget);
+
+// A portal that can't be used yet
+#[derive(Component)]
+pub struct LockedPortal;
+
+#[derive(Component)]
+pub struct SpawnPoint(pub String);
+
+// Added to the guy when a portal moves him within a level, so that he isn't
+// immediately sent back by a portal at the other end. Removed once he's no
+// longer touching any portal
+#[derive(Component)]
+pub struct JustTeleported;
+
+// Text shown above a portal, naming the level it leads to
+#[derive(Component)]
+pub struct PortalLabel;
 
 #[derive(Bundle)]
 pub struct PortalBundle {
@@ -175,10 +609,10 @@ impl PortalBundle {
     pub fn new(
         texture_handle: &Handle<Image>,
         at: Vec3,
-        level_path: PathBuf,
+        target: PortalTarget,
     ) -> Self {
         PortalBundle {
-            portal: Portal(level_path),
+            portal: Portal(target),
             sprite: SpriteBundle {
                 texture: texture_handle.clone(),
                 transform: Transform {
@@ -195,6 +629,318 @@ impl PortalBundle {
     }
 }
 
+#[derive(Bundle)]
+pub struct PortalLabelBundle {
+    label: PortalLabel,
+    #[bundle]
+    text: Text2dBundle,
+}
+
+impl PortalLabelBundle {
+    const FONT_SIZE: f32 = 12.0;
+    // relative to the portal. The text sits on top of this point
+    const OFFSET: Vec3 = Vec3::new(0.0, 10.0, 1.0);
+    // the nerd font's check mark
+    const COMPLETED_MARK: char = '\u{f00c}';
+
+    // Shows the player's progress on the level, if the portal leads to one
+    pub fn new(
+        name: String,
+        progress: Option<&LevelProgress>,
+        font: &Handle<Font>,
+    ) -> Self {
+        let mut label = name;
+        if let Some(progress) = progress {
+            if progress.completed {
+                label.push(' ');
+                label.push(Self::COMPLETED_MARK);
+            }
+            if let Some(best_steps) = progress.best_steps {
+                label.push_str(&format!("\n{}", format_time(best_steps)));
+            }
+            if progress.total_collectibles > 0 {
+                label.push_str(&format!(
+                    "\n{}/{}",
+                    progress.best_collectibles, progress.total_collectibles
+                ));
+            }
+        }
+
+        PortalLabelBundle {
+            label: PortalLabel,
+            text: Text2dBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: Self::FONT_SIZE,
+                        // invisible until the guy gets close, see
+                        // portal_label_fade_system
+                        color: Color::rgba(0.0, 0.0, 0.0, 0.0),
+                    },
+                )
+                .with_alignment(TextAlignment::BOTTOM_CENTER),
+                transform: Transform::from_translation(Self::OFFSET),
+                ..default()
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Collectible;
+
+#[derive(Bundle)]
+pub struct CollectibleBundle {
+    collectible: Collectible,
+    #[bundle]
+    sprite_sheet: SpriteSheetBundle,
+    aabb: Aabb,
+    draw_aabb: DrawAabb,
+}
+
+impl CollectibleBundle {
+    // the coin in tiles_packed.png
+    const TILE_INDEX: usize = 151;
+    const SCALE: Vec2 = Vec2::new(10., 10.);
+
+    pub fn new(translation: Vec3, texture_atlas: &Handle<TextureAtlas>) -> Self {
+        CollectibleBundle {
+            collectible: Collectible,
+            sprite_sheet: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: Self::TILE_INDEX,
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                texture_atlas: texture_atlas.clone(),
+                ..default()
+            },
+            aabb: Aabb::StaticAabb {
+                scale: &Self::SCALE,
+            },
+            draw_aabb: DrawAabb,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Checkpoint {
+    // in the level's tile grid, which is what its splits are recorded against
+    pub position: IVec2,
+    // whether the guy has touched it yet this run
+    pub reached: bool,
+}
+
+#[derive(Bundle)]
+pub struct CheckpointBundle {
+    checkpoint: Checkpoint,
+    #[bundle]
+    sprite_sheet: SpriteSheetBundle,
+    aabb: Aabb,
+    draw_aabb: DrawAabb,
+}
+
+impl CheckpointBundle {
+    // the flag in tiles_packed.png
+    const TILE_INDEX: usize = 111;
+    const SCALE: Vec2 = Vec2::new(12., 18.);
+    // checkpoints are faded out until they're reached
+    const UNREACHED_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+    pub const REACHED_COLOR: Color = Color::WHITE;
+
+    pub fn new(
+        position: IVec2,
+        translation: Vec3,
+        texture_atlas: &Handle<TextureAtlas>,
+    ) -> Self {
+        CheckpointBundle {
+            checkpoint: Checkpoint {
+                position,
+                reached: false,
+            },
+            sprite_sheet: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: Self::TILE_INDEX,
+                    color: Self::UNREACHED_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                texture_atlas: texture_atlas.clone(),
+                ..default()
+            },
+            aabb: Aabb::StaticAabb {
+                scale: &Self::SCALE,
+            },
+            draw_aabb: DrawAabb,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ExtraLife;
+
+#[derive(Bundle)]
+pub struct ExtraLifeBundle {
+    extra_life: ExtraLife,
+    #[bundle]
+    sprite_sheet: SpriteSheetBundle,
+    aabb: Aabb,
+    draw_aabb: DrawAabb,
+}
+
+impl ExtraLifeBundle {
+    // the heart in tiles_packed.png
+    const TILE_INDEX: usize = 44;
+    const SCALE: Vec2 = Vec2::new(12., 12.);
+
+    pub fn new(translation: Vec3, texture_atlas: &Handle<TextureAtlas>) -> Self {
+        ExtraLifeBundle {
+            extra_life: ExtraLife,
+            sprite_sheet: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: Self::TILE_INDEX,
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                texture_atlas: texture_atlas.clone(),
+                ..default()
+            },
+            aabb: Aabb::StaticAabb {
+                scale: &Self::SCALE,
+            },
+            draw_aabb: DrawAabb,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Hazard;
+
+#[derive(Bundle)]
+pub struct HazardBundle {
+    hazard: Hazard,
+    #[bundle]
+    sprite_sheet: SpriteSheetBundle,
+    aabb: Aabb,
+    draw_aabb: DrawAabb,
+}
+
+impl HazardBundle {
+    // tiles in tiles_packed.png. There's no lava, so it's tinted water
+    const SPIKES_TILE_INDEX: usize = 68;
+    const LAVA_SURFACE_TILE_INDEX: usize = 33;
+    const LAVA_TILE_INDEX: usize = 53;
+    const LAVA_COLOR: Color = Color::rgb(1.0, 0.35, 0.1);
+    // a little smaller than the tiles, so that grazing a hazard is forgiven
+    const SPIKES_SCALE: Vec2 = Vec2::new(14., 8.);
+    const LAVA_SCALE: Vec2 = Vec2::new(16., 14.);
+
+    // `surface` is whether the top of the hazard is exposed, which only matters
+    // for lava
+    pub fn new(
+        kind: HazardKind,
+        surface: bool,
+        translation: Vec3,
+        texture_atlas: &Handle<TextureAtlas>,
+    ) -> Self {
+        let (index, color, scale) = match kind {
+            HazardKind::Spikes => {
+                (Self::SPIKES_TILE_INDEX, Color::WHITE, &Self::SPIKES_SCALE)
+            }
+            HazardKind::Lava if surface => (
+                Self::LAVA_SURFACE_TILE_INDEX,
+                Self::LAVA_COLOR,
+                &Self::LAVA_SCALE,
+            ),
+            HazardKind::Lava => {
+                (Self::LAVA_TILE_INDEX, Self::LAVA_COLOR, &Self::LAVA_SCALE)
+            }
+        };
+        HazardBundle {
+            hazard: Hazard,
+            sprite_sheet: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index,
+                    color,
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                texture_atlas: texture_atlas.clone(),
+                ..default()
+            },
+            aabb: Aabb::StaticAabb { scale },
+            draw_aabb: DrawAabb,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Goal;
+
+#[derive(Bundle)]
+pub struct GoalBundle {
+    goal: Goal,
+    #[bundle]
+    sprite: SpriteBundle,
+    aabb: Aabb,
+    draw_aabb: DrawAabb,
+}
+
+impl GoalBundle {
+    const GOAL_SIZE: Vec2 = Vec2::new(12., 18.);
+
+    pub fn new(at: Vec3) -> Self {
+        GoalBundle {
+            goal: Goal,
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: Color::GOLD,
+                    custom_size: Some(Self::GOAL_SIZE),
+                    ..default()
+                },
+                transform: Transform::from_translation(at),
+                ..default()
+            },
+            aabb: Aabb::StaticAabb {
+                scale: &Self::GOAL_SIZE,
+            },
+            draw_aabb: DrawAabb,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct DecorationSprite;
+
+#[derive(Bundle)]
+pub struct DecorationBundle {
+    decoration: DecorationSprite,
+    #[bundle]
+    sprite_sheet: SpriteSheetBundle,
+}
+
+impl DecorationBundle {
+    pub fn new(
+        tile_index: usize,
+        translation: Vec3,
+        texture_atlas: &Handle<TextureAtlas>,
+    ) -> Self {
+        DecorationBundle {
+            decoration: DecorationSprite,
+            sprite_sheet: SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: tile_index,
+                    ..default()
+                },
+                transform: Transform::from_translation(translation),
+                texture_atlas: texture_atlas.clone(),
+                ..default()
+            },
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct TileBundle {
     #[bundle]
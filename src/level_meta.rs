@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::level::PortalTarget;
+
+pub const DEFAULT_TILESET: &str = "tiles_packed.png";
+
+// Optional settings for a level, read from a RON file alongside the level image,
+// eg `level1.ron` for `level1.png`. Every field can be left out.
+//
+// (
+//     name: Some("The Big Drop"),
+//     author: Some("sully"),
+//     par_time: Some(30.0),
+//     clear_color: Some(Rgba(red: 0.2, green: 0.2, blue: 0.3, alpha: 1.0)),
+//     tileset: Some("tiles_packed.png"),
+//     music: Some("music/big_drop.ogg"),
+//     // indexed by the green channel of portal pixels
+//     portals: [
+//         Level(path: "level2.png"),
+//         Level(path: "level3.png", spawn: Some("balcony")),
+//         Spawn("cellar"),
+//         // a level from the level pack
+//         LevelId("first_steps"),
+//     ],
+//     // indexed by the green channel of spawn point pixels
+//     spawn_points: ["cellar"],
+//     // indexed by the green channel of gate pixels
+//     gates: [["first_steps"], ["first_steps", "the_big_drop"]],
+// )
+#[derive(Deserialize, TypeUuid, Clone, Debug, Default)]
+#[uuid = "5b7d2c1e-8f4a-4c39-9a7e-2d61f0b3c8a4"]
+#[serde(default)]
+pub struct LevelMeta {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    // in seconds
+    pub par_time: Option<f32>,
+    pub clear_color: Option<Color>,
+    // path relative to the assets directory. Must use the same layout as
+    // tiles_packed.png, since autotiling depends on it
+    pub tileset: Option<String>,
+    // path relative to the assets directory
+    pub music: Option<String>,
+    pub portals: Vec<PortalTarget>,
+    pub spawn_points: Vec<String>,
+    // each lists the ids of the levels that must be completed for the gate to
+    // open
+    pub gates: Vec<Vec<String>>,
+}
+
+impl LevelMeta {
+    pub fn sidecar_path(level_path: &Path) -> PathBuf {
+        level_path.with_extension("ron")
+    }
+
+    // Reads the sidecar directly from the filesystem rather than going through
+    // the AssetServer. Returns Ok(None) if there isn't one.
+    pub fn try_read_sidecar(
+        level_path: &Path,
+    ) -> Result<Option<Self>, ron::error::SpannedError> {
+        let sidecar_path = Self::sidecar_path(level_path);
+        let Ok(contents) = std::fs::read_to_string(sidecar_path) else {
+            return Ok(None);
+        };
+        ron::from_str(&contents).map(Some)
+    }
+
+    // Like try_read_sidecar, but logs parse errors and carries on without any
+    // metadata
+    pub fn read_sidecar(level_path: &Path) -> Option<Self> {
+        Self::try_read_sidecar(level_path)
+            .map_err(|e| {
+                let sidecar_path = Self::sidecar_path(level_path);
+                warn!("failed to parse {}: {}", sidecar_path.display(), e);
+            })
+            .ok()
+            .flatten()
+    }
+
+    // The name to show for the level at `level_path`, falling back to the file
+    // name if there's no name in the metadata
+    pub fn display_name(&self, level_path: &Path) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            level_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn tileset(&self) -> &str {
+        self.tileset.as_deref().unwrap_or(DEFAULT_TILESET)
+    }
+}
+
+#[derive(Default)]
+pub struct LevelMetaLoader;
+
+impl AssetLoader for LevelMetaLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let meta: LevelMeta = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(meta));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+// The metadata of the level that's currently being played
+#[derive(Resource, Default)]
+pub struct CurrentLevelMeta(pub LevelMeta);
+
+// Handle to the music that's playing for the current level, so we can stop it
+// when we leave
+#[derive(Resource)]
+pub struct LevelMusic(pub Handle<AudioSink>);
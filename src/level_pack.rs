@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::level_meta::LevelMeta;
+
+// relative to the assets directory
+pub const LEVEL_PACK_PATH: &str = "levels.pack.ron";
+
+// The levels in the game, in order, and optionally an authored overworld level
+// linking them together. Without an overworld, one is generated with a portal
+// for each level. Portals can refer to levels by id with
+// `PortalTarget::LevelId`. Paths are relative to the assets directory.
+//
+// (
+//     overworld: Some("overworld.png"),
+//     levels: [
+//         (id: "first_steps", path: "level1.png"),
+//         (id: "the_big_drop", path: "level2.png"),
+//     ],
+// )
+#[derive(Deserialize, Clone, Debug)]
+pub struct LevelPackManifest {
+    #[serde(default)]
+    pub overworld: Option<PathBuf>,
+    pub levels: Vec<LevelPackManifestEntry>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LevelPackManifestEntry {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+pub struct LevelPackEntry {
+    pub id: String,
+    pub path: PathBuf,
+    pub name: String,
+}
+
+// A manifest with the name of each level already read from its sidecar file
+#[derive(TypeUuid)]
+#[uuid = "e3a8c6f2-41d7-4b0e-9c5a-7f2b81d4e96c"]
+pub struct LevelPack {
+    pub overworld: Option<PathBuf>,
+    pub levels: Vec<LevelPackEntry>,
+}
+
+impl LevelPack {
+    pub fn entry(&self, id: &str) -> Option<&LevelPackEntry> {
+        self.levels.iter().find(|entry| entry.id == id)
+    }
+}
+
+#[derive(Default)]
+pub struct LevelPackLoader;
+
+impl AssetLoader for LevelPackLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest: LevelPackManifest = ron::de::from_bytes(bytes)?;
+            let mut levels = Vec::new();
+            for LevelPackManifestEntry { id, path } in manifest.levels {
+                let sidecar_path = LevelMeta::sidecar_path(&path);
+                // a missing or broken sidecar just means the level has no name
+                let meta = match load_context.read_asset_bytes(&sidecar_path).await {
+                    Ok(sidecar) => parse_sidecar(&sidecar_path, &sidecar),
+                    Err(_) => LevelMeta::default(),
+                };
+                let name = meta.display_name(&path);
+                levels.push(LevelPackEntry { id, path, name });
+            }
+            load_context.set_default_asset(LoadedAsset::new(LevelPack {
+                overworld: manifest.overworld,
+                levels,
+            }));
+            Ok(())
+        })
+    }
+
+    // more specific than LevelMetaLoader's "ron", so it takes priority
+    fn extensions(&self) -> &[&str] {
+        &["pack.ron"]
+    }
+}
+
+fn parse_sidecar(sidecar_path: &Path, bytes: &[u8]) -> LevelMeta {
+    ron::de::from_bytes(bytes).unwrap_or_else(|e| {
+        warn!("failed to parse {}: {}", sidecar_path.display(), e);
+        LevelMeta::default()
+    })
+}
+
+// Kept for the whole game so the pack stays loaded between visits to the
+// overworld
+#[derive(Resource)]
+pub struct LevelPackHandle(pub Handle<LevelPack>);
+
+impl FromWorld for LevelPackHandle {
+    fn from_world(world: &mut World) -> Self {
+        LevelPackHandle(world.resource::<AssetServer>().load(LEVEL_PACK_PATH))
+    }
+}
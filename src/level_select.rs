@@ -0,0 +1,118 @@
+#![allow(clippy::type_complexity)]
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+use leafwing_input_manager::prelude::*;
+
+use crate::input::GlobalAction;
+use crate::level::LoadingLevel;
+use crate::level_pack::{LevelPack, LevelPackHandle};
+use crate::lives::{refill_lives, LivesConfig};
+use crate::menu::{
+    menu_node, menu_screen_node, Menu, MenuConfirmed, MenuItemBundle,
+};
+use crate::platformer::{AppState, GameFont};
+use crate::save::SaveData;
+use crate::speedrun::format_time;
+use crate::state_transitions::despawn_where;
+use crate::transition::{load_level, LevelRequest};
+
+#[derive(Component)]
+pub struct LevelSelectScreen;
+
+// The level pack might not have finished loading when the screen is shown, so
+// the menu starts empty and is filled in by level_select_system
+#[derive(Component)]
+pub struct LevelSelectMenu;
+
+// The level a menu item loads
+#[derive(Component)]
+pub struct LevelSelectOption(pub PathBuf);
+
+// the nerd font's check mark
+const COMPLETED_MARK: char = '\u{f00c}';
+
+pub fn enter_level_select(mut commands: Commands, font: Res<GameFont>) {
+    let heading_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 50.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn(menu_screen_node(Color::NONE))
+        .insert(LevelSelectScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("LEVEL SELECT", heading_style));
+            parent
+                .spawn(menu_node())
+                .insert(Menu::default())
+                .insert(LevelSelectMenu);
+        });
+}
+
+pub fn level_select_system(
+    empty_menu_query: Query<Entity, (With<LevelSelectMenu>, Without<Children>)>,
+    global_action_state: Res<ActionState<GlobalAction>>,
+    mut confirmed: EventReader<MenuConfirmed>,
+    option_query: Query<&LevelSelectOption>,
+    level_pack_handle: Res<LevelPackHandle>,
+    level_packs: Res<Assets<LevelPack>>,
+    save: Res<SaveData>,
+    font: Res<GameFont>,
+    lives_config: Res<LivesConfig>,
+    mut commands: Commands,
+) {
+    if global_action_state.just_pressed(GlobalAction::Back) {
+        commands.insert_resource(NextState(AppState::MainMenu));
+        return;
+    }
+
+    if let (Ok(menu), Some(level_pack)) = (
+        empty_menu_query.get_single(),
+        level_packs.get(&level_pack_handle.0),
+    ) {
+        commands.entity(menu).with_children(|menu| {
+            for entry in level_pack.levels.iter() {
+                let mut label = entry.name.clone();
+                if let Some(progress) = save.level(&entry.path) {
+                    if progress.completed {
+                        label.push(' ');
+                        label.push(COMPLETED_MARK);
+                    }
+                    if let Some(best_steps) = progress.best_steps {
+                        label.push_str(&format!("  {}", format_time(best_steps)));
+                    }
+                    if progress.total_collectibles > 0 {
+                        label.push_str(&format!(
+                            "  {}/{}",
+                            progress.best_collectibles, progress.total_collectibles
+                        ));
+                    }
+                }
+                menu.spawn(MenuItemBundle::new(label, &font.0))
+                    .insert(LevelSelectOption(entry.path.clone()));
+            }
+        });
+    }
+
+    for MenuConfirmed(item) in confirmed.iter() {
+        let Ok(LevelSelectOption(path)) = option_query.get(*item) else {
+            continue;
+        };
+        refill_lives(&mut commands, &lives_config);
+        load_level(
+            &mut commands,
+            LevelRequest::new(LoadingLevel::Path(path.clone())),
+        );
+    }
+}
+
+pub fn exit_level_select(
+    to_despawn: Query<Entity, With<LevelSelectScreen>>,
+    commands: Commands,
+) {
+    despawn_where(to_despawn, commands);
+}
@@ -0,0 +1,23 @@
+pub mod bindings;
+pub mod death;
+pub mod debug_overlay;
+pub mod frame_step;
+pub mod guy;
+pub mod hud;
+pub mod input;
+pub mod level;
+pub mod level_meta;
+pub mod level_pack;
+pub mod level_select;
+pub mod lives;
+pub mod main_menu;
+pub mod menu;
+pub mod physics_object;
+pub mod platformer;
+pub mod plugins;
+pub mod reachability;
+pub mod save;
+pub mod settings;
+pub mod speedrun;
+pub mod state_transitions;
+pub mod transition;
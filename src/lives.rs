@@ -0,0 +1,93 @@
+#![allow(clippy::type_complexity)]
+
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
+
+use crate::death::PlayerDied;
+use crate::guy::Guy;
+use crate::level::ExtraLife;
+use crate::platformer::Aabb;
+
+// How many lives the guy gets. With starting_lives set to None there are no
+// lives at all and the guy can die as often as he likes.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LivesConfig {
+    pub starting_lives: Option<u32>,
+    // extra life pickups don't go past this
+    pub max_lives: u32,
+}
+
+impl Default for LivesConfig {
+    fn default() -> Self {
+        LivesConfig {
+            starting_lives: Some(3),
+            max_lives: 9,
+        }
+    }
+}
+
+// Only exists while lives are turned on. Running out ends in AppState::GameOver
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lives(pub u32);
+
+// Gives the guy a full set of lives, at the start of the game and after a game
+// over
+pub fn refill_lives(commands: &mut Commands, config: &LivesConfig) {
+    match config.starting_lives {
+        Some(lives) => commands.insert_resource(Lives(lives)),
+        None => commands.remove_resource::<Lives>(),
+    }
+}
+
+pub fn setup_lives(mut commands: Commands, config: Res<LivesConfig>) {
+    refill_lives(&mut commands, &config);
+}
+
+pub fn lose_life(
+    mut player_died: EventReader<PlayerDied>,
+    mut lives: ResMut<Lives>,
+) {
+    let deaths = player_died.iter().count() as u32;
+    if deaths > 0 {
+        lives.0 = lives.0.saturating_sub(deaths);
+    }
+}
+
+// Extra lives are picked up even with lives turned off, they just don't do
+// anything
+pub fn extra_life_system(
+    guy_query: Query<(&Transform, &Aabb), With<Guy>>,
+    extra_life_query: Query<
+        (Entity, &Transform, &Aabb),
+        (With<ExtraLife>, Without<Guy>),
+    >,
+    config: Res<LivesConfig>,
+    lives: Option<ResMut<Lives>>,
+    mut commands: Commands,
+) {
+    let Ok((guy_transform, guy_aabb)) = guy_query.get_single() else {
+        return;
+    };
+    let guy_size = guy_aabb.get_scale(guy_transform);
+
+    let mut picked_up = 0;
+    for (extra_life, extra_life_transform, extra_life_aabb) in
+        extra_life_query.iter()
+    {
+        let collision = collide(
+            extra_life_transform.translation,
+            extra_life_aabb.get_scale(extra_life_transform),
+            guy_transform.translation,
+            guy_size,
+        );
+        if collision.is_none() {
+            continue;
+        }
+        commands.entity(extra_life).despawn_recursive();
+        picked_up += 1;
+    }
+
+    if let Some(mut lives) = lives.filter(|_| picked_up > 0) {
+        lives.0 = (lives.0 + picked_up).min(config.max_lives);
+    }
+}
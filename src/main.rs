@@ -1,33 +1,22 @@
-mod guy;
-mod input;
-mod level;
-mod physics_object;
-mod platformer;
-mod state_transitions;
-
 use bevy::log::LogPlugin;
-use bevy::utils::Duration;
 use bevy::{diagnostic::LogDiagnosticsPlugin, prelude::*};
-use bevy_prototype_debug_lines::*;
-use input::{
-    game_input_system, global_input_system, make_global_input_map, GameAction,
-    GlobalAction,
-};
-use iyes_loopless::{fixedtimestep::FixedTimestepStageLabel, prelude::*};
-use leafwing_input_manager::prelude::*;
-use platformer::{
-    draw_aabbs, guy_collision_system, move_camera, physics_system, setup,
-    update_jump_state, AppState, PHYSICS_TIME_STEP, TIME_STEP,
+use platformer::bindings::Bindings;
+use platformer::lives::LivesConfig;
+use platformer::platformer::{BASE_HEIGHT, BASE_WIDTH, DEFAULT_CLEAR_COLOR};
+use platformer::plugins::{
+    DebugPlugin, InputPlugin, LevelPlugin, PhysicsPlugin, PlayerPlugin, UiPlugin,
 };
-use state_transitions::*;
+use platformer::save::SaveData;
+use platformer::speedrun::SpeedrunConfig;
+use platformer::transition::TransitionStyle;
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins
             .set(WindowPlugin {
                 window: WindowDescriptor {
-                    width: 640.0,
-                    height: 360.0,
+                    width: BASE_WIDTH,
+                    height: BASE_HEIGHT,
                     // width: 960.0,
                     // height: 540.0,
                     ..default()
@@ -42,67 +31,19 @@ fn main() {
             .set(ImagePlugin::default_nearest())
         )
         .add_plugin(LogDiagnosticsPlugin::default())
-        .add_plugin(InputManagerPlugin::<GlobalAction>::default())
-        .add_plugin(InputManagerPlugin::<GameAction>::default())
-        .add_plugin(DebugLinesPlugin::default())
-        .insert_resource(ActionState::<GlobalAction>::default())
-        .insert_resource(make_global_input_map())
-        .insert_resource(ClearColor(Color::rgb(0.7, 0.8, 0.9)))
-        .add_fixed_timestep(Duration::from_secs_f32(TIME_STEP), "input_timestep")
-        .add_fixed_timestep_system(
-            "input_timestep",
-            0,
-            global_input_system.label("global_input"),
-        )
-        .add_fixed_timestep_system(
-            "input_timestep",
-            0,
-            game_input_system
-                .run_in_state(AppState::InGame)
-                .label("game_input"),
-        )
-        .add_fixed_timestep_after_stage(
-            FixedTimestepStageLabel("input_timestep"),
-            Duration::from_secs_f32(PHYSICS_TIME_STEP),
-            "physics_timestep",
-        )
-        .add_fixed_timestep_system(
-            "physics_timestep",
-            0,
-            physics_system
-                .run_in_state(AppState::InGame)
-                .label("physics"),
-        )
-        .add_fixed_timestep_system(
-            "physics_timestep",
-            0,
-            guy_collision_system
-                .run_in_state(AppState::InGame)
-                .label("guy_collision")
-                .after("physics"),
-        )
-        .add_fixed_timestep_system(
-            "physics_timestep",
-            0,
-            update_jump_state
-                .run_in_state(AppState::InGame)
-                .after("guy_collision")
-                .label("update_jump_state"),
-        )
-        .add_system(
-            move_camera
-                .run_in_state(AppState::InGame)
-                .after("guy_collision"),
-        )
-        .add_startup_system(setup)
-        .add_system(draw_aabbs)
-        .add_system(bevy::window::close_on_esc)
-        .add_loopless_state(AppState::MainMenu)
-        .add_enter_system(AppState::Loading, enter_loading)
-        .add_enter_system(AppState::Loading, despawn_level_contents)
-        .add_system(wait_level_load.run_in_state(AppState::Loading))
-        .add_exit_system(AppState::Loading, exit_loading)
-        .add_enter_system(AppState::Paused, enter_paused)
-        .add_exit_system(AppState::Paused, exit_paused)
+        .insert_resource(ClearColor(DEFAULT_CLEAR_COLOR))
+        // set starting_lives to None to play without lives
+        .insert_resource(LivesConfig::default())
+        .insert_resource(TransitionStyle::Iris)
+        // set show_splits to false to keep checkpoint splits off the HUD
+        .insert_resource(SpeedrunConfig::default())
+        .insert_resource(Bindings::load())
+        .insert_resource(SaveData::load())
+        .add_plugin(InputPlugin)
+        .add_plugin(PhysicsPlugin)
+        .add_plugin(PlayerPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(UiPlugin)
+        .add_plugin(DebugPlugin)
         .run();
 }
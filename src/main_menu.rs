@@ -0,0 +1,93 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+
+use crate::level::LoadingLevel;
+use crate::lives::{refill_lives, LivesConfig};
+use crate::menu::{
+    menu_node, menu_screen_node, Menu, MenuConfirmed, MenuItemBundle,
+};
+use crate::platformer::{AppState, GameFont};
+use crate::settings::SettingsReturnTo;
+use crate::state_transitions::despawn_where;
+use crate::transition::{load_level, LevelRequest};
+
+#[derive(Component)]
+pub struct MainMenuScreen;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub enum MainMenuOption {
+    Play,
+    LevelSelect,
+    Settings,
+    Quit,
+}
+
+pub fn enter_main_menu(mut commands: Commands, font: Res<GameFont>) {
+    let title_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 60.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn(menu_screen_node(Color::NONE))
+        .insert(MainMenuScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("JUMP RECTANGLE", title_style));
+            parent
+                .spawn(menu_node())
+                .insert(Menu::default())
+                .with_children(|menu| {
+                    menu.spawn(MenuItemBundle::new("play", &font.0))
+                        .insert(MainMenuOption::Play);
+                    menu.spawn(MenuItemBundle::new("level select", &font.0))
+                        .insert(MainMenuOption::LevelSelect);
+                    menu.spawn(MenuItemBundle::new("settings", &font.0))
+                        .insert(MainMenuOption::Settings);
+                    menu.spawn(MenuItemBundle::new("quit", &font.0))
+                        .insert(MainMenuOption::Quit);
+                });
+        });
+}
+
+pub fn main_menu_system(
+    mut confirmed: EventReader<MenuConfirmed>,
+    option_query: Query<&MainMenuOption>,
+    lives_config: Res<LivesConfig>,
+    mut exit: EventWriter<AppExit>,
+    mut commands: Commands,
+) {
+    for MenuConfirmed(item) in confirmed.iter() {
+        let Ok(option) = option_query.get(*item) else {
+            continue;
+        };
+        match option {
+            MainMenuOption::Play => {
+                // I don't really love this approach of needing to insert a
+                // LoadingLevel when I switch to the loading state
+                // not sure if there's a better way to communicate between states.
+                refill_lives(&mut commands, &lives_config);
+                load_level(
+                    &mut commands,
+                    LevelRequest::new(LoadingLevel::Overworld),
+                );
+            }
+            MainMenuOption::LevelSelect => {
+                commands.insert_resource(NextState(AppState::LevelSelect))
+            }
+            MainMenuOption::Settings => {
+                commands.insert_resource(SettingsReturnTo(AppState::MainMenu));
+                commands.insert_resource(NextState(AppState::Settings));
+            }
+            MainMenuOption::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+pub fn exit_main_menu(
+    to_despawn: Query<Entity, With<MainMenuScreen>>,
+    commands: Commands,
+) {
+    despawn_where(to_despawn, commands);
+}
@@ -0,0 +1,195 @@
+#![allow(clippy::type_complexity)]
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::input::GlobalAction;
+
+// A vertical list of text options. Its MenuItems are its children, in order,
+// and are moved between with up/down and chosen with confirm (or start)
+#[derive(Component, Default)]
+pub struct Menu {
+    pub selected: usize,
+    // for menus shown in states where start already does something else
+    pub ignore_start: bool,
+}
+
+// Ignores input to the menu until the timer finishes, for menus that appear
+// while the player might be pressing buttons for something else
+#[derive(Component)]
+pub struct MenuInputDelay(pub Timer);
+
+impl MenuInputDelay {
+    pub fn from_seconds(duration: f32) -> Self {
+        MenuInputDelay(Timer::from_seconds(duration, TimerMode::Once))
+    }
+}
+
+#[derive(Component)]
+pub struct MenuItem {
+    // disabled items are shown but can't be selected
+    pub enabled: bool,
+}
+
+#[derive(Bundle)]
+pub struct MenuItemBundle {
+    item: MenuItem,
+    #[bundle]
+    text: TextBundle,
+}
+
+impl MenuItemBundle {
+    pub fn new(label: impl Into<String>, font: &Handle<Font>) -> Self {
+        MenuItemBundle {
+            item: MenuItem { enabled: true },
+            text: TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: UNSELECTED_COLOR,
+                },
+            ),
+        }
+    }
+
+    pub fn disabled(label: impl Into<String>, font: &Handle<Font>) -> Self {
+        let mut item = Self::new(label, font);
+        item.item.enabled = false;
+        item.text.text.sections[0].style.color = DISABLED_COLOR;
+        item
+    }
+
+    // for menus too long to fit on screen at the usual size
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.text.text.sections[0].style.font_size = font_size;
+        self
+    }
+}
+
+const SELECTED_COLOR: Color = Color::BLACK;
+const UNSELECTED_COLOR: Color = Color::GRAY;
+const DISABLED_COLOR: Color = Color::rgba(0.5, 0.5, 0.5, 0.4);
+
+// A full screen column that menu screens put their title and Menu in
+pub fn menu_screen_node(background_color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            // ColumnReverse lays children out from the top down
+            flex_direction: FlexDirection::ColumnReverse,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: background_color.into(),
+        ..default()
+    }
+}
+
+// The node to put a Menu on
+pub fn menu_node() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            margin: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+// Sent with the MenuItem entity that was chosen. Whoever spawned the menu
+// should put something on its items to tell them apart.
+pub struct MenuConfirmed(pub Entity);
+
+pub fn menu_system(
+    time: Res<Time>,
+    global_action_state: Res<ActionState<GlobalAction>>,
+    mut menu_query: Query<(&mut Menu, &Children, Option<&mut MenuInputDelay>)>,
+    mut item_query: Query<(&MenuItem, &mut Text)>,
+    mut confirmed: EventWriter<MenuConfirmed>,
+) {
+    for (mut menu, children, input_delay) in menu_query.iter_mut() {
+        let ignore_input = match input_delay {
+            Some(mut input_delay) => !input_delay.0.tick(time.delta()).finished(),
+            None => false,
+        };
+        let items: Vec<(Entity, bool)> = children
+            .iter()
+            .filter_map(|child| {
+                let (item, _) = item_query.get(*child).ok()?;
+                Some((*child, item.enabled))
+            })
+            .collect();
+        if !items.iter().any(|(_, enabled)| *enabled) {
+            continue;
+        }
+
+        // step through the items in the given direction until we land on one
+        // that can be selected
+        let len = items.len();
+        let step = |from: usize, forward: bool| {
+            let mut i = from;
+            loop {
+                i = if forward {
+                    (i + 1) % len
+                } else {
+                    (i + len - 1) % len
+                };
+                if items[i].1 {
+                    return i;
+                }
+            }
+        };
+        let mut selected = menu.selected.min(len - 1);
+        if !items[selected].1 {
+            selected = step(selected, true);
+        }
+        if !ignore_input && global_action_state.just_pressed(GlobalAction::Up) {
+            selected = step(selected, false);
+        }
+        if !ignore_input && global_action_state.just_pressed(GlobalAction::Down) {
+            selected = step(selected, true);
+        }
+        if menu.selected != selected {
+            menu.selected = selected;
+        }
+
+        for (i, (item, enabled)) in items.iter().enumerate() {
+            let Ok((_, mut text)) = item_query.get_mut(*item) else {
+                continue;
+            };
+            let color = if !enabled {
+                DISABLED_COLOR
+            } else if i == menu.selected {
+                SELECTED_COLOR
+            } else {
+                UNSELECTED_COLOR
+            };
+            // avoid touching the text when nothing's changed, so it isn't
+            // laid out again every frame
+            if text
+                .sections
+                .iter()
+                .all(|section| section.style.color == color)
+            {
+                continue;
+            }
+            for section in text.sections.iter_mut() {
+                section.style.color = color;
+            }
+        }
+
+        if ignore_input {
+            continue;
+        }
+        if global_action_state.just_pressed(GlobalAction::Confirm)
+            || (global_action_state.just_pressed(GlobalAction::Start)
+                && !menu.ignore_start)
+        {
+            confirmed.send(MenuConfirmed(items[menu.selected].0));
+        }
+    }
+}
@@ -9,14 +9,25 @@ use bevy_prototype_debug_lines::*;
 use iyes_loopless::state::NextState;
 // use rand::prelude::*;
 
+use crate::bindings::Bindings;
+use crate::hud::spawn_hud;
 use crate::level::*;
+use crate::save::SaveData;
+use crate::speedrun::{LevelTimer, Split};
+use crate::transition::{load_level, LevelRequest};
 use crate::{
     guy::*,
     physics_object::{Gravity, PhysicsObject},
 };
 
+// the window's starting size, which the UI is laid out for
+pub const BASE_WIDTH: f32 = 640.0;
+pub const BASE_HEIGHT: f32 = 360.0;
+
 pub const TIME_STEP: f32 = 1. / 60.0;
 pub const PHYSICS_TIME_STEP: f32 = 1.0 / 120.0;
+// change in vertical velocity per physics step
+pub const GRAVITY: f32 = 23.0;
 
 // pub fn make_level_1() -> Level {
 //     let wall_thickness = 10.0;
@@ -136,10 +147,26 @@ impl Default for Aabb {
 //     }
 // }
 
-#[derive(Component, PartialEq, Eq)]
-pub struct PauseMessage;
+pub const DEFAULT_CLEAR_COLOR: Color = Color::rgb(0.7, 0.8, 0.9);
+// tints the portal sprite
+const LOCKED_PORTAL_COLOR: Color = Color::rgba(0.3, 0.3, 0.3, 0.6);
+// the lock block in tiles_packed.png
+const GATE_TILE_INDEX: usize = 28;
+
+#[derive(Resource)]
+pub struct GameFont(pub Handle<Font>);
+
+impl FromWorld for GameFont {
+    fn from_world(world: &mut World) -> Self {
+        GameFont(
+            world
+                .resource::<AssetServer>()
+                .load("fonts/AL Ubuntu Mono Nerd Font Complete.ttf"),
+        )
+    }
+}
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn setup(mut commands: Commands, font: Res<GameFont>) {
     // Add the game's entities to our world
 
     // cameras
@@ -151,24 +178,7 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         },
         ..Default::default()
     });
-    // Pause message
-    commands
-        .spawn(
-            TextBundle::from_section(
-                "PAUSED",
-                TextStyle {
-                    font: asset_server
-                        .load("fonts/AL Ubuntu Mono Nerd Font Complete.ttf"),
-                    font_size: 100.0,
-                    color: Color::BLACK,
-                },
-            ) // Set the alignment of the Text
-            .with_text_alignment(TextAlignment::CENTER)
-            // Set the style of the TextBundle itself.
-            .with_style(default()),
-        )
-        .insert(PauseMessage)
-        .insert(Visibility { is_visible: false });
+    spawn_hud(&mut commands, &font.0);
 }
 
 #[derive(Component)]
@@ -184,7 +194,7 @@ pub fn draw_aabbs(
     }
 }
 
-fn draw_rect_colored(
+pub fn draw_rect_colored(
     lines: &mut DebugLines,
     Rect { min, max }: Rect,
     duration: f32,
@@ -271,22 +281,38 @@ fn autotile_code_to_spritesheet_index(n: usize) -> usize {
     }
 }
 
+// Converts a position in the level's tile grid to world space
+pub fn tile_to_world(IVec2 { x, y }: IVec2, z: f32) -> Vec3 {
+    // -y because image coordinates treat down as positive y direction
+    const TILE_WIDTH: i32 = 18;
+    Vec3::new((x * TILE_WIDTH) as f32, -1.0 * (y * TILE_WIDTH) as f32, z)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_level(
     commands: &mut Commands,
     tile_texture_atlas_handle: Handle<TextureAtlas>,
     portal_image_handle: Handle<Image>,
+    font: &Handle<Font>,
+    save: &SaveData,
+    bindings: &Bindings,
     level: &Level,
+    spawn_at: Option<&str>,
 ) {
-    for (position @ IVec2 { x, y }, level_contents_type) in &level.0 {
-        // -y because image coordinates treat down as positive y direction
-        const TILE_WIDTH: i32 = 18;
-        let translation =
-            Vec3::new((x * TILE_WIDTH) as f32, -1.0 * (y * TILE_WIDTH) as f32, 0.0);
+    // if we've been asked to start at a spawn point the level doesn't have, fall
+    // back to the usual player position
+    let spawn_at = spawn_at.filter(|spawn| level.spawn_point(spawn).is_some());
+
+    for (position, level_contents_type) in &level.0 {
+        let translation = tile_to_world(*position, LEVEL_CONTENTS_Z);
         match level_contents_type {
             LevelContents::Player => {
-                commands
-                    .spawn(GuyBundle::with_translation(translation))
-                    .insert(DrawAabb);
+                if spawn_at.is_none() {
+                    commands
+                        .spawn(GuyBundle::with_translation(translation, bindings))
+                        .insert(DrawAabb);
+                    commands.insert_resource(RespawnPoint(translation));
+                }
             }
             LevelContents::Tile => {
                 let tile_index = autotile_code_to_spritesheet_index(
@@ -300,16 +326,114 @@ pub fn spawn_level(
                     ))
                     .insert(DrawAabb);
             }
-            LevelContents::Portal(level_path) => {
-                commands.spawn(PortalBundle::new(
+            LevelContents::Portal {
+                target,
+                name,
+                locked,
+            } => {
+                // should have been resolved into PortalTarget::Level when the
+                // level was loaded, and there's nowhere for it to lead otherwise
+                if let PortalTarget::LevelId(id) = target {
+                    warn!("not spawning portal to unresolved level id {id}");
+                    continue;
+                }
+                let mut portal = commands.spawn(PortalBundle::new(
                     &portal_image_handle,
                     translation,
-                    level_path.to_path_buf(),
+                    target.clone(),
+                ));
+                if *locked {
+                    portal.insert(LockedPortal).insert(Sprite {
+                        color: LOCKED_PORTAL_COLOR,
+                        ..default()
+                    });
+                }
+                if let Some(name) = name {
+                    let progress = match target {
+                        PortalTarget::Level { path, .. } => save.level(path),
+                        _ => None,
+                    };
+                    portal.with_children(|parent| {
+                        parent.spawn(PortalLabelBundle::new(
+                            name.clone(),
+                            progress,
+                            font,
+                        ));
+                    });
+                }
+            }
+            LevelContents::Goal => {
+                commands.spawn(GoalBundle::new(translation));
+            }
+            LevelContents::Collectible => {
+                commands.spawn(CollectibleBundle::new(
+                    translation,
+                    &tile_texture_atlas_handle,
+                ));
+            }
+            LevelContents::Hazard(kind) => {
+                // y points down in the level grid
+                let above = level.0.get(&(*position - IVec2::Y));
+                let surface =
+                    !matches!(above, Some(LevelContents::Hazard(k)) if k == kind);
+                commands.spawn(HazardBundle::new(
+                    *kind,
+                    surface,
+                    translation,
+                    &tile_texture_atlas_handle,
+                ));
+            }
+            LevelContents::Checkpoint => {
+                commands.spawn(CheckpointBundle::new(
+                    *position,
+                    translation,
+                    &tile_texture_atlas_handle,
+                ));
+            }
+            LevelContents::ExtraLife => {
+                commands.spawn(ExtraLifeBundle::new(
+                    translation,
+                    &tile_texture_atlas_handle,
                 ));
             }
+            // gates that are open have already been removed from the level
+            LevelContents::Gate { .. } => {
+                commands
+                    .spawn(TileBundle::new(
+                        GATE_TILE_INDEX,
+                        translation,
+                        &tile_texture_atlas_handle,
+                    ))
+                    .insert(DrawAabb);
+            }
+            LevelContents::SpawnPoint(spawn) => {
+                commands.spawn((
+                    SpawnPoint(spawn.clone()),
+                    TransformBundle::from_transform(Transform::from_translation(
+                        translation,
+                    )),
+                ));
+                if spawn_at == Some(spawn.as_str()) {
+                    // there might be a portal here, we don't want to go straight
+                    // back through it
+                    commands
+                        .spawn(GuyBundle::with_translation(translation, bindings))
+                        .insert(DrawAabb)
+                        .insert(JustTeleported);
+                    commands.insert_resource(RespawnPoint(translation));
+                }
+            }
         }
     }
 
+    for (position, Decoration { tile_index, layer }) in &level.1 {
+        commands.spawn(DecorationBundle::new(
+            *tile_index,
+            tile_to_world(*position, layer.z()),
+            &tile_texture_atlas_handle,
+        ));
+    }
+
     // let level1 = make_level_1();
     // add_level_walls(commands, &level1);
 }
@@ -319,7 +443,7 @@ pub fn physics_system(
 ) {
     for (_entity, mut physics, mut transform, gravity) in query.iter_mut() {
         if gravity.is_some() {
-            physics.velocity.y -= 23.0;
+            physics.velocity.y -= GRAVITY;
         }
 
         // move
@@ -330,7 +454,12 @@ pub fn physics_system(
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Resource)]
+// Where the guy touched walls in the last physics step, and which way each wall
+// pushed him. Only used by the debug overlay.
+#[derive(Resource, Default, Debug)]
+pub struct GuyContacts(pub Vec<(Vec2, Vec2)>);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Resource)]
 pub enum AppState {
     MainMenu,
     // Invariant: We can only transition to AppState::InGame if we have inserted a LoadedLevel
@@ -338,25 +467,54 @@ pub enum AppState {
     InGame,
     Loading,
     Paused,
+    // showing the results screen after reaching a level's goal
+    LevelComplete,
+    // the guy has run out of lives
+    GameOver,
+    // picking a level to play from the main menu
+    LevelSelect,
+    // rebinding controls, from the main or pause menu
+    Settings,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn guy_collision_system(
     time: Res<Time>,
     mut guy_query: Query<
-        (&mut PhysicsObject, &mut Transform, &Aabb, &mut JumpState),
+        (
+            Entity,
+            &mut PhysicsObject,
+            &mut Transform,
+            &Aabb,
+            &mut JumpState,
+            Option<&JustTeleported>,
+        ),
         (With<Guy>, Without<Wall>),
     >,
     wall_query: Query<(&Transform, &Aabb), (With<Wall>, Without<Guy>)>,
-    portal_query: Query<(&Portal, &Transform, &Aabb), Without<Guy>>,
+    portal_query: Query<
+        (&Portal, &Transform, &Aabb),
+        (Without<Guy>, Without<LockedPortal>),
+    >,
+    spawn_point_query: Query<(&SpawnPoint, &Transform), Without<Guy>>,
+    loading_level: Res<LoadingLevel>,
+    mut contacts: ResMut<GuyContacts>,
     mut commands: Commands,
 ) {
-    let (mut guy_physics, mut guy_transform, &guy_aabb, mut jump_state) =
-        guy_query.single_mut();
+    let (
+        guy_entity,
+        mut guy_physics,
+        mut guy_transform,
+        &guy_aabb,
+        mut jump_state,
+        just_teleported,
+    ) = guy_query.single_mut();
 
     let guy_size = guy_aabb.get_scale(&guy_transform);
 
     // PORTAL COLLISIONS
-    for (portal, portal_transform, portal_aabb) in portal_query.iter() {
+    let mut touching_portal = false;
+    for (Portal(target), portal_transform, portal_aabb) in portal_query.iter() {
         let portal_size = portal_aabb.get_scale(portal_transform);
         let collision = collide(
             portal_transform.translation,
@@ -365,15 +523,53 @@ pub fn guy_collision_system(
             guy_size,
         );
 
-        if collision.is_some() {
-            commands.insert_resource(NextState(AppState::Loading));
-            commands.insert_resource(LoadingLevel::Path(portal.0.clone()));
+        if collision.is_none() {
+            continue;
+        }
+        touching_portal = true;
+        if just_teleported.is_some() {
+            continue;
         }
+
+        match target {
+            PortalTarget::Level { path, spawn } => {
+                let return_portal = match *loading_level {
+                    LoadingLevel::Overworld => Some(path.clone()),
+                    LoadingLevel::Path(_) => None,
+                };
+                load_level(
+                    &mut commands,
+                    LevelRequest {
+                        level: LoadingLevel::Path(path.clone()),
+                        spawn_point: spawn.clone(),
+                        return_portal,
+                    },
+                );
+            }
+            PortalTarget::Spawn(spawn) => {
+                let destination = spawn_point_query
+                    .iter()
+                    .find(|(SpawnPoint(name), _)| name == spawn);
+                if let Some((_, destination)) = destination {
+                    guy_transform.translation = destination.translation;
+                    guy_physics.old_position = destination.translation;
+                    guy_physics.velocity = Vec2::ZERO;
+                    commands.entity(guy_entity).insert(JustTeleported);
+                }
+            }
+            // never spawned, see spawn_level
+            PortalTarget::LevelId(_) => (),
+        }
+        break;
+    }
+    if just_teleported.is_some() && !touching_portal {
+        commands.entity(guy_entity).remove::<JustTeleported>();
     }
 
     // WALL COLLISIONS
     // assume we're in the air until proven otherwise
     jump_state.on_ground = None;
+    contacts.0.clear();
     jump_state.coyote_timer.tick(time.delta());
 
     for (wall_transform, wall_aabb) in wall_query.iter() {
@@ -419,17 +615,211 @@ pub fn guy_collision_system(
             }
             None => (),
         }
+        let normal = match collision {
+            Some(Collision::Left) => Vec2::X,
+            Some(Collision::Right) => Vec2::NEG_X,
+            Some(Collision::Top) => Vec2::NEG_Y,
+            Some(Collision::Bottom) => Vec2::Y,
+            _ => continue,
+        };
+        // the middle of the guy's side that's touching the wall
+        let point = guy_transform.translation.truncate() - normal * guy_size / 2.0;
+        contacts.0.push((point, normal));
+    }
+}
+
+// Portal labels fade in as the guy gets closer to them
+pub fn portal_label_fade_system(
+    guy_query: Query<&Transform, With<Guy>>,
+    mut label_query: Query<(&GlobalTransform, &mut Text), With<PortalLabel>>,
+) {
+    // in world units
+    const FADE_START: f32 = 120.0;
+    const FADE_END: f32 = 50.0;
+
+    let Ok(guy_transform) = guy_query.get_single() else {
+        return;
+    };
+    for (label_transform, mut text) in label_query.iter_mut() {
+        let distance = label_transform
+            .translation()
+            .truncate()
+            .distance(guy_transform.translation.truncate());
+        let alpha =
+            ((FADE_START - distance) / (FADE_START - FADE_END)).clamp(0.0, 1.0);
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+pub fn collectible_system(
+    guy_query: Query<(&Transform, &Aabb), With<Guy>>,
+    collectible_query: Query<
+        (Entity, &Transform, &Aabb),
+        (With<Collectible>, Without<Guy>),
+    >,
+    mut tally: ResMut<CollectibleTally>,
+    mut commands: Commands,
+) {
+    let Ok((guy_transform, guy_aabb)) = guy_query.get_single() else {
+        return;
+    };
+    let guy_size = guy_aabb.get_scale(guy_transform);
+
+    for (collectible, collectible_transform, collectible_aabb) in
+        collectible_query.iter()
+    {
+        let collision = collide(
+            collectible_transform.translation,
+            collectible_aabb.get_scale(collectible_transform),
+            guy_transform.translation,
+            guy_size,
+        );
+        if collision.is_none() {
+            continue;
+        }
+        commands.entity(collectible).despawn_recursive();
+        tally.collected += 1;
+    }
+}
+
+pub fn checkpoint_system(
+    guy_query: Query<(&Transform, &Aabb), With<Guy>>,
+    mut checkpoint_query: Query<
+        (&mut Checkpoint, &mut TextureAtlasSprite, &Transform, &Aabb),
+        Without<Guy>,
+    >,
+    mut respawn_point: ResMut<RespawnPoint>,
+    mut level_timer: ResMut<LevelTimer>,
+) {
+    let Ok((guy_transform, guy_aabb)) = guy_query.get_single() else {
+        return;
+    };
+    let guy_size = guy_aabb.get_scale(guy_transform);
+
+    for (mut checkpoint, mut sprite, checkpoint_transform, checkpoint_aabb) in
+        checkpoint_query.iter_mut()
+    {
+        let collision = collide(
+            checkpoint_transform.translation,
+            checkpoint_aabb.get_scale(checkpoint_transform),
+            guy_transform.translation,
+            guy_size,
+        );
+        if collision.is_none() {
+            continue;
+        }
+        respawn_point.0 = checkpoint_transform.translation;
+        // split the first time each checkpoint is reached
+        if !checkpoint.reached {
+            checkpoint.reached = true;
+            sprite.color = CheckpointBundle::REACHED_COLOR;
+            let split = Split {
+                checkpoint: checkpoint.position,
+                steps: level_timer.steps,
+            };
+            level_timer.splits.push(split);
+        }
+    }
+}
+
+// Puts the guy back at the last checkpoint without reloading the level
+pub fn respawn_system(
+    mut respawn: EventReader<RespawnGuy>,
+    respawn_point: Res<RespawnPoint>,
+    mut guy_query: Query<
+        (Entity, &mut Transform, &mut PhysicsObject, &mut JumpState),
+        With<Guy>,
+    >,
+    mut commands: Commands,
+) {
+    if respawn.iter().last().is_none() {
+        return;
+    }
+    let Ok((guy_entity, mut transform, mut physics, mut jump_state)) =
+        guy_query.get_single_mut()
+    else {
+        return;
+    };
+    transform.translation = respawn_point.0;
+    transform.rotation = Quat::IDENTITY;
+    transform.scale = GUY_SIZE.extend(0.);
+    physics.velocity = Vec2::ZERO;
+    physics.old_position = respawn_point.0;
+    *jump_state = JumpState::default();
+    // the respawn point might be a spawn point with a portal on it
+    commands.entity(guy_entity).insert(JustTeleported);
+}
+
+pub fn goal_system(
+    guy_query: Query<(&Transform, &Aabb), With<Guy>>,
+    goal_query: Query<(Entity, &Transform, &Aabb), (With<Goal>, Without<Guy>)>,
+    loading_level: Res<LoadingLevel>,
+    level_timer: Res<LevelTimer>,
+    collectibles: Res<CollectibleTally>,
+    mut level_completed: EventWriter<LevelCompleted>,
+    mut commands: Commands,
+) {
+    let Ok((guy_transform, guy_aabb)) = guy_query.get_single() else { return };
+    let guy_size = guy_aabb.get_scale(guy_transform);
+
+    for (goal, goal_transform, goal_aabb) in goal_query.iter() {
+        let collision = collide(
+            goal_transform.translation,
+            goal_aabb.get_scale(goal_transform),
+            guy_transform.translation,
+            guy_size,
+        );
+        if collision.is_none() {
+            continue;
+        }
+
+        // there may be more physics steps before the state changes, make sure we
+        // only complete the level once
+        commands.entity(goal).despawn_recursive();
+        if let LoadingLevel::Path(level_path) = &*loading_level {
+            level_completed.send(LevelCompleted {
+                level_path: level_path.clone(),
+                steps: level_timer.steps,
+                splits: level_timer.splits.clone(),
+                collectibles: *collectibles,
+            });
+        }
+        commands.insert_resource(NextState(AppState::LevelComplete));
+        break;
     }
 }
 
 pub fn move_camera(
-    mut camera: Query<&mut Transform, (With<Camera>, Without<Guy>)>,
+    mut camera: Query<
+        (&mut Transform, &OrthographicProjection),
+        (With<Camera>, Without<Guy>),
+    >,
     player: Query<&Transform, (With<Guy>, Without<Camera>)>,
+    bounds: Option<Res<LevelBounds>>,
 ) {
     let Ok(player) = player.get_single() else { return };
-    let Ok(mut transform) = camera.get_single_mut() else { return };
+    let Ok((mut transform, projection)) = camera.get_single_mut() else { return };
+
+    let mut target = player.translation.truncate();
+    // don't show anything outside the level, unless the level is smaller than
+    // the screen, in which case keep it centred
+    if let Some(LevelBounds(Rect { min, max })) = bounds.as_deref() {
+        let half_view = Vec2::new(
+            projection.right - projection.left,
+            projection.top - projection.bottom,
+        ) * projection.scale
+            / 2.0;
+        let (min, max) = (*min + half_view, *max - half_view);
+        let centre = (min + max) / 2.0;
+        let clamped = target.clamp(min, max.max(min));
+        target = Vec2::select(min.cmple(max), clamped, centre);
+    }
 
-    let guy_pos = player.translation;
+    // keep the camera's own z, or things drawn in front of the guy would end up
+    // behind it
+    let guy_pos = target.extend(transform.translation.z);
     let camera_pos: Vec3 = transform.translation;
 
     // i don't even know what the units are
@@ -0,0 +1,439 @@
+// The game split up into plugins, so that tools and tests can build an App
+// with only the parts they need, eg without any rendering.
+//
+// Each plugin sets up the fixed timesteps and states it adds systems to, and
+// initialises the resources its systems read, so any of them can be added on
+// its own. The ones that order their systems around the physics add
+// PhysicsPlugin if it isn't there yet, so if you add it yourself, add it
+// before them. Everything apart from InputPlugin, PhysicsPlugin and
+// PlayerPlugin loads assets, so needs the AssetPlugin.
+//
+// Resources are only initialised if they aren't already there, so insert them
+// before adding the plugins to change them. That includes Bindings and
+// SaveData, which start out as the defaults rather than being read from disk.
+
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+use bevy::utils::Duration;
+use bevy_prototype_debug_lines::DebugLinesPlugin;
+use iyes_loopless::{fixedtimestep::FixedTimestepStageLabel, prelude::*};
+use leafwing_input_manager::prelude::*;
+
+use crate::bindings::Bindings;
+use crate::death::{
+    count_deaths, dying_system, exit_dying, hazard_system, kill_plane_system,
+    DeathCount, PlayState, PlayerDied,
+};
+use crate::debug_overlay::{
+    clear_debug_trail, debug_trail_system, draw_debug_overlay,
+    toggle_debug_overlay_system, DebugOverlay,
+};
+use crate::frame_step::{
+    frame_step_clock_system, frame_step_input_system, stop_frame_stepping,
+    FrameStepping,
+};
+use crate::guy::RespawnGuy;
+use crate::hud::{hide_hud, hud_system, show_hud, ui_scale_system};
+use crate::input::{
+    apply_bindings_system, game_input_system, global_input_system,
+    make_global_input_map, GameAction, GlobalAction,
+};
+use crate::level::{CollectibleTally, LevelCompleted, LoadingLevel};
+use crate::level_meta::{LevelMeta, LevelMetaLoader};
+use crate::level_pack::{LevelPack, LevelPackHandle, LevelPackLoader};
+use crate::level_select::{
+    enter_level_select, exit_level_select, level_select_system,
+};
+use crate::lives::{extra_life_system, lose_life, setup_lives, Lives, LivesConfig};
+use crate::main_menu::{enter_main_menu, exit_main_menu, main_menu_system};
+use crate::menu::{menu_system, MenuConfirmed};
+use crate::platformer::{
+    checkpoint_system, collectible_system, draw_aabbs, goal_system,
+    guy_collision_system, move_camera, physics_system, portal_label_fade_system,
+    respawn_system, setup, update_jump_state, AppState, GameFont, GuyContacts,
+    DEFAULT_CLEAR_COLOR, PHYSICS_TIME_STEP, TIME_STEP,
+};
+use crate::save::{save_on_level_completed, SaveData};
+use crate::settings::{
+    enter_settings, exit_settings, rebinding_system, settings_labels_system,
+    settings_menu_system, Rebinding,
+};
+use crate::speedrun::{level_timer_system, LevelTimer, SpeedrunConfig};
+use crate::state_transitions::*;
+use crate::transition::{
+    spawn_transition_overlay, transition_overlay_system, transition_system,
+    Transition, TransitionStyle,
+};
+
+// the fixed timesteps
+pub const INPUT_TIMESTEP: &str = "input_timestep";
+pub const PHYSICS_TIMESTEP: &str = "physics_timestep";
+
+// Marks that add_schedule has already run
+#[derive(Resource)]
+struct ScheduleAdded;
+
+// The fixed timesteps, AppState and PlayState, and the system that switches
+// states at the end of a transition. Every plugin calls this first, and only
+// the first call adds anything. The states are added after the timesteps so
+// that state changes asked for in a fixed timestep happen in the same frame.
+fn add_schedule(app: &mut App) {
+    if app.world.contains_resource::<ScheduleAdded>() {
+        return;
+    }
+    app.insert_resource(ScheduleAdded)
+        .add_fixed_timestep(Duration::from_secs_f32(TIME_STEP), INPUT_TIMESTEP)
+        .add_fixed_timestep_after_stage(
+            FixedTimestepStageLabel(INPUT_TIMESTEP),
+            Duration::from_secs_f32(PHYSICS_TIME_STEP),
+            PHYSICS_TIMESTEP,
+        )
+        .add_loopless_state(AppState::MainMenu)
+        .add_loopless_state(PlayState::Playing)
+        .add_system(transition_system.label("transition"));
+}
+
+// For plugins with systems ordered around "physics" and "guy_collision"
+fn add_physics(app: &mut App) {
+    if !app.is_plugin_added::<PhysicsPlugin>() {
+        app.add_plugin(PhysicsPlugin);
+    }
+}
+
+// The input maps and the global actions like start
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        add_schedule(app);
+        app.init_resource::<Bindings>();
+        let global_input_map =
+            make_global_input_map(app.world.resource::<Bindings>());
+        app.add_plugin(InputManagerPlugin::<GlobalAction>::default())
+            .add_plugin(InputManagerPlugin::<GameAction>::default())
+            .init_resource::<ActionState<GlobalAction>>()
+            .insert_resource(global_input_map)
+            .add_fixed_timestep_system(
+                INPUT_TIMESTEP,
+                0,
+                global_input_system
+                    .run_unless_resource_exists::<Transition>()
+                    .label("global_input"),
+            )
+            .add_system(apply_bindings_system);
+    }
+}
+
+// Moving things and pushing the guy out of walls, in the physics timestep
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        add_schedule(app);
+        app.init_resource::<GuyContacts>()
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                physics_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("physics"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                guy_collision_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("guy_collision")
+                    .after("physics"),
+            );
+    }
+}
+
+// Controlling the guy, jumping, dying and lives, and the camera following him
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        add_schedule(app);
+        add_physics(app);
+        app.init_resource::<DeathCount>()
+            .init_resource::<LivesConfig>()
+            .add_event::<RespawnGuy>()
+            .add_event::<PlayerDied>()
+            .add_startup_system(setup_lives)
+            .add_fixed_timestep_system(
+                INPUT_TIMESTEP,
+                0,
+                game_input_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("game_input"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                respawn_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("respawn")
+                    .before("physics"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                hazard_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .after("guy_collision"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                extra_life_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .after("guy_collision"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                kill_plane_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .after("guy_collision"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                update_jump_state
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .after("guy_collision")
+                    .label("update_jump_state"),
+            )
+            .add_system(
+                move_camera
+                    .run_in_state(AppState::InGame)
+                    .after("guy_collision"),
+            )
+            .add_system(count_deaths)
+            .add_system(lose_life.run_if_resource_exists::<Lives>())
+            .add_system(
+                dying_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Dying),
+            )
+            .add_exit_system(PlayState::Dying, exit_dying);
+    }
+}
+
+// Loading and spawning levels, and everything in them the guy can touch apart
+// from hazards, as well as the level timer and saving progress
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        add_schedule(app);
+        add_physics(app);
+        // the renderer adds a ClearColor of its own, so main inserts the
+        // game's. This is for when there's no renderer.
+        if !app.world.contains_resource::<ClearColor>() {
+            app.insert_resource(ClearColor(DEFAULT_CLEAR_COLOR));
+        }
+        app.init_resource::<SaveData>()
+            .init_resource::<Bindings>()
+            .init_resource::<LevelTimer>()
+            .init_resource::<CollectibleTally>()
+            .init_resource::<SpeedrunConfig>()
+            .init_resource::<GameFont>()
+            .init_resource::<LevelPackHandle>()
+            .add_asset::<LevelMeta>()
+            .init_asset_loader::<LevelMetaLoader>()
+            .add_asset::<LevelPack>()
+            .init_asset_loader::<LevelPackLoader>()
+            .add_event::<LevelCompleted>()
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                level_timer_system
+                    .run_in_state(AppState::InGame)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("level_timer")
+                    .before("physics"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                collectible_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("collectibles")
+                    .after("guy_collision"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                checkpoint_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .after("guy_collision"),
+            )
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                goal_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .label("goal")
+                    .after("collectibles"),
+            )
+            .add_system(portal_label_fade_system.run_in_state(AppState::InGame))
+            .add_system(save_on_level_completed)
+            .add_enter_system(AppState::Loading, enter_loading)
+            .add_enter_system(AppState::Loading, despawn_level_contents)
+            .add_system(
+                wait_level_load
+                    .run_in_state(AppState::Loading)
+                    .run_if_resource_exists::<LoadingLevelImageHandle>()
+                    .run_unless_resource_exists::<LoadingFailed>(),
+            )
+            .add_system(
+                wait_overworld_load
+                    .run_in_state(AppState::Loading)
+                    .run_if_resource_equals(LoadingLevel::Overworld)
+                    .run_unless_resource_exists::<LoadingLevelImageHandle>()
+                    .run_unless_resource_exists::<LoadingFailed>(),
+            )
+            .add_exit_system(AppState::Loading, exit_loading);
+    }
+}
+
+// The camera, menus, HUD, and the screens between levels
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        add_schedule(app);
+        app.init_resource::<TransitionStyle>()
+            .init_resource::<SaveData>()
+            .init_resource::<Bindings>()
+            .init_resource::<ActionState<GlobalAction>>()
+            .init_resource::<LivesConfig>()
+            .init_resource::<DeathCount>()
+            .init_resource::<LevelTimer>()
+            .init_resource::<CollectibleTally>()
+            .init_resource::<SpeedrunConfig>()
+            .init_resource::<GameFont>()
+            .init_resource::<LevelPackHandle>()
+            .add_event::<MenuConfirmed>()
+            .add_event::<RespawnGuy>()
+            .add_startup_system(setup)
+            .add_startup_system(spawn_transition_overlay)
+            .add_system(
+                menu_system
+                    .run_unless_resource_exists::<Rebinding>()
+                    .run_unless_resource_exists::<Transition>(),
+            )
+            .add_system(transition_overlay_system.after("transition"))
+            .add_system(hud_system)
+            .add_system(ui_scale_system)
+            .add_enter_system(AppState::InGame, show_hud)
+            .add_exit_system(AppState::InGame, hide_hud)
+            .add_enter_system(AppState::Loading, spawn_loading_screen)
+            .add_system(loading_screen_system.run_in_state(AppState::Loading))
+            .add_system(
+                loading_failed_system
+                    .run_in_state(AppState::Loading)
+                    .run_if_resource_exists::<LoadingFailed>()
+                    .run_unless_resource_exists::<Transition>(),
+            )
+            .add_exit_system(AppState::Loading, despawn_loading_screen)
+            .add_enter_system(AppState::MainMenu, enter_main_menu)
+            .add_system(main_menu_system.run_in_state(AppState::MainMenu))
+            .add_exit_system(AppState::MainMenu, exit_main_menu)
+            .add_enter_system(AppState::LevelSelect, enter_level_select)
+            .add_system(
+                level_select_system
+                    .run_in_state(AppState::LevelSelect)
+                    .run_unless_resource_exists::<Transition>(),
+            )
+            .add_exit_system(AppState::LevelSelect, exit_level_select)
+            .add_enter_system(AppState::Settings, enter_settings)
+            .add_system(
+                settings_menu_system
+                    .run_in_state(AppState::Settings)
+                    .run_unless_resource_exists::<Rebinding>()
+                    .run_unless_resource_exists::<Transition>(),
+            )
+            .add_system(
+                rebinding_system
+                    .run_in_state(AppState::Settings)
+                    .run_if_resource_exists::<Rebinding>(),
+            )
+            .add_system(settings_labels_system.run_in_state(AppState::Settings))
+            .add_exit_system(AppState::Settings, exit_settings)
+            .add_enter_system(AppState::Paused, enter_paused)
+            .add_system(
+                pause_menu_system
+                    .run_in_state(AppState::Paused)
+                    .run_unless_resource_exists::<Transition>(),
+            )
+            .add_exit_system(AppState::Paused, exit_paused)
+            .add_enter_system(AppState::LevelComplete, enter_level_complete)
+            .add_system(results_screen_system.run_in_state(AppState::LevelComplete))
+            .add_exit_system(AppState::LevelComplete, exit_level_complete)
+            .add_enter_system(AppState::GameOver, enter_game_over)
+            .add_system(game_over_menu_system.run_in_state(AppState::GameOver))
+            .add_exit_system(AppState::GameOver, exit_game_over);
+    }
+}
+
+// Frame-stepping and the debug overlay
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        add_schedule(app);
+        add_physics(app);
+        app.init_resource::<ActionState<GlobalAction>>()
+            .init_resource::<GameFont>()
+            .add_event::<RespawnGuy>()
+            .add_plugin(DebugLinesPlugin::default())
+            .add_system(frame_step_input_system)
+            .add_system_to_stage(
+                CoreStage::First,
+                frame_step_clock_system
+                    .run_if_resource_exists::<FrameStepping>()
+                    .after(TimeSystem),
+            )
+            .add_exit_system(AppState::InGame, stop_frame_stepping)
+            .add_system(toggle_debug_overlay_system)
+            .add_fixed_timestep_system(
+                PHYSICS_TIMESTEP,
+                0,
+                debug_trail_system
+                    .run_in_state(AppState::InGame)
+                    .run_in_state(PlayState::Playing)
+                    .run_unless_resource_exists::<Transition>()
+                    .run_if_resource_exists::<DebugOverlay>()
+                    .after("guy_collision"),
+            )
+            .add_enter_system(AppState::Loading, clear_debug_trail)
+            .add_system(clear_debug_trail.run_on_event::<RespawnGuy>())
+            .add_system(draw_aabbs.run_if_resource_exists::<DebugOverlay>())
+            .add_system(draw_debug_overlay.run_if_resource_exists::<DebugOverlay>());
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::guy::{GUY_H_SPEED, JUMP_SPEED};
+use crate::level::{Level, LevelContents, PortalTarget, TileBundle};
+use crate::platformer::{GRAVITY, PHYSICS_TIME_STEP};
+
+// A rough approximation of what the guy can get to in a level, used by the
+// level validator.
+//
+// Rather than running the real physics, we treat the guy as a point at the
+// centre of his bounding box moving through the tile grid, using the same jump
+// speed, gravity and horizontal speed as the game. Each jump holds a single
+// direction for the whole arc, possibly after a delay, which covers most of
+// what a player would actually do. Portals to spawn points within the level are
+// followed. Gates are treated as open, since which ones are depends on the save
+// file. Coyote jumps and pre-jumps are ignored, so this mostly errs on the side
+// of calling things unreachable. The exception is hazards, which are ignored
+// too, so a route straight through some spikes still counts.
+//
+// Positions are in units of tiles, with y pointing down, matching `Level`.
+
+// how many physics steps we simulate a single jump or fall for before giving up
+const MAX_STEPS: usize = 600;
+
+// how many physics steps to wait before holding a direction mid-jump
+const STEER_DELAYS: [usize; 4] = [0, 10, 20, 40];
+
+pub struct Reachability {
+    // every tile the guy's centre passes through, whether standing, walking,
+    // jumping or falling
+    visited: HashSet<IVec2>,
+}
+
+impl Reachability {
+    pub fn reaches(&self, position: IVec2) -> bool {
+        self.visited.contains(&position)
+    }
+}
+
+// Things that the player needs to be able to get to for a level to be
+// completable
+pub fn is_target(level_contents: &LevelContents) -> bool {
+    matches!(
+        level_contents,
+        LevelContents::Portal { .. }
+            | LevelContents::Goal
+            | LevelContents::Collectible
+    )
+}
+
+fn is_solid(level: &Level, position: IVec2) -> bool {
+    matches!(level.0.get(&position), Some(LevelContents::Tile))
+}
+
+fn is_standable(level: &Level, position: IVec2) -> bool {
+    !is_solid(level, position) && is_solid(level, position + IVec2::Y)
+}
+
+fn tile_of(position: Vec2) -> IVec2 {
+    position.round().as_ivec2()
+}
+
+// Simulates the guy leaving `start` with an upward velocity of `jump_speed`,
+// holding `direction` once `delay` steps have passed. Every tile passed through
+// is added to `visited`. Returns the tile he lands on, if he lands at all.
+fn simulate_arc(
+    level: &Level,
+    start: IVec2,
+    jump_speed: f32,
+    direction: f32,
+    delay: usize,
+    lowest_row: i32,
+    visited: &mut HashSet<IVec2>,
+) -> Option<IVec2> {
+    let tile_width = TileBundle::TILE_SIZE.x;
+    let mut position = start.as_vec2();
+    let mut velocity_up = jump_speed;
+
+    for step in 0..MAX_STEPS {
+        velocity_up -= GRAVITY;
+        let velocity_x = if step >= delay {
+            direction * GUY_H_SPEED
+        } else {
+            0.0
+        };
+        // -velocity_up because y points down
+        let delta =
+            Vec2::new(velocity_x, -velocity_up) * PHYSICS_TIME_STEP / tile_width;
+
+        let next = Vec2::new(position.x + delta.x, position.y);
+        if !is_solid(level, tile_of(next)) {
+            position = next;
+        }
+
+        let next = Vec2::new(position.x, position.y + delta.y);
+        if !is_solid(level, tile_of(next)) {
+            position = next;
+        } else if delta.y > 0.0 {
+            return Some(tile_of(position));
+        } else {
+            // bumped our head
+            velocity_up = velocity_up.min(0.0);
+        }
+
+        let tile = tile_of(position);
+        visited.insert(tile);
+        if tile.y > lowest_row {
+            // fell out of the level
+            return None;
+        }
+    }
+
+    None
+}
+
+// Finds every standing position reachable by walking and jumping from those
+// already in `queue`
+fn explore(
+    level: &Level,
+    queue: &mut VecDeque<IVec2>,
+    standing: &mut HashSet<IVec2>,
+    lowest_row: i32,
+    visited: &mut HashSet<IVec2>,
+) {
+    while let Some(from) = queue.pop_front() {
+        visited.insert(from);
+        let mut landings: Vec<IVec2> = Vec::new();
+
+        // walking, and walking off ledges
+        for direction in [-1, 1] {
+            let next = from + IVec2::new(direction, 0);
+            if is_solid(level, next) {
+                continue;
+            }
+            visited.insert(next);
+            if is_standable(level, next) {
+                landings.push(next);
+            } else {
+                for steer in [direction as f32, 0.0] {
+                    landings.extend(simulate_arc(
+                        level, next, 0.0, steer, 0, lowest_row, visited,
+                    ));
+                }
+            }
+        }
+
+        // jumping
+        for direction in [-1.0, 0.0, 1.0] {
+            for delay in STEER_DELAYS {
+                landings.extend(simulate_arc(
+                    level, from, JUMP_SPEED, direction, delay, lowest_row, visited,
+                ));
+            }
+        }
+
+        for landing in landings {
+            if standing.insert(landing) {
+                queue.push_back(landing);
+            }
+        }
+    }
+}
+
+pub fn analyse(level: &Level) -> Reachability {
+    let mut visited = HashSet::new();
+    let lowest_row = level.0.keys().map(|position| position.y).max().unwrap_or(0);
+
+    let spawn = level
+        .0
+        .iter()
+        .find_map(|(position, contents)| match contents {
+            LevelContents::Player => Some(*position),
+            _ => None,
+        });
+    let Some(spawn) = spawn else {
+        return Reachability { visited };
+    };
+
+    let mut standing: HashSet<IVec2> = HashSet::new();
+    let mut queue: VecDeque<IVec2> = VecDeque::new();
+
+    // Places the guy can appear, either by spawning or by going through a
+    // portal to a spawn point in the same level. He starts in the air, and falls
+    // to the ground from there.
+    let mut entry_points: Vec<IVec2> = vec![spawn];
+    let mut entered: HashSet<IVec2> = HashSet::new();
+
+    while let Some(entry_point) = entry_points.pop() {
+        if !entered.insert(entry_point) {
+            continue;
+        }
+        visited.insert(entry_point);
+        if let Some(landed) =
+            simulate_arc(level, entry_point, 0.0, 0.0, 0, lowest_row, &mut visited)
+        {
+            if standing.insert(landed) {
+                queue.push_back(landed);
+            }
+        }
+
+        explore(level, &mut queue, &mut standing, lowest_row, &mut visited);
+
+        entry_points.extend(level.0.iter().filter_map(|(position, contents)| {
+            match contents {
+                LevelContents::Portal {
+                    target: PortalTarget::Spawn(spawn),
+                    ..
+                } if visited.contains(position) => level.spawn_point(spawn),
+                _ => None,
+            }
+        }));
+    }
+
+    Reachability { visited }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::level_meta::LevelMeta;
+
+    // Builds a level from a picture of it, one character per pixel: '#' is a
+    // tile, 'P' the player, 'G' the goal, 'o' a portal to the meta's first
+    // portal target and 's' its first spawn point. Anything else is empty.
+    fn parse_level(picture: &str, meta: &LevelMeta) -> Level {
+        let rows: Vec<&str> = picture
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect();
+        let mut image = RgbaImage::new(rows[0].len() as u32, rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let pixel = match c {
+                    '#' => Rgba([0, 0, 0, 255]),
+                    'P' => Rgba([255, 0, 0, 255]),
+                    'G' => Rgba([0, 255, 0, 255]),
+                    'o' => Rgba([0, 0, 255, 255]),
+                    's' => Rgba([255, 0, 255, 255]),
+                    _ => continue,
+                };
+                image.put_pixel(x as u32, y as u32, pixel);
+            }
+        }
+        Level::from_rgba(&image, meta).unwrap()
+    }
+
+    fn reaches_goal(level: &Level) -> bool {
+        let goal = level
+            .0
+            .iter()
+            .find_map(|(position, contents)| match contents {
+                LevelContents::Goal => Some(*position),
+                _ => None,
+            })
+            .unwrap();
+        analyse(level).reaches(goal)
+    }
+
+    #[test]
+    fn jumps_a_one_tile_gap() {
+        let level = parse_level(
+            "
+            P.......G
+            ####.####
+            ",
+            &LevelMeta::default(),
+        );
+        assert!(reaches_goal(&level));
+    }
+
+    #[test]
+    fn reaches_a_low_ledge() {
+        let level = parse_level(
+            "
+            ....G
+            ....#
+            ....#
+            P...#
+            #####
+            ",
+            &LevelMeta::default(),
+        );
+        assert!(reaches_goal(&level));
+    }
+
+    #[test]
+    fn cant_reach_a_ledge_higher_than_a_jump() {
+        let level = parse_level(
+            "
+            ......G
+            ......#
+            ......#
+            ......#
+            ......#
+            ......#
+            P.....#
+            #######
+            ",
+            &LevelMeta::default(),
+        );
+        assert!(!reaches_goal(&level));
+    }
+
+    #[test]
+    fn follows_portals_to_spawn_points() {
+        let meta = LevelMeta {
+            portals: vec![PortalTarget::Spawn("other room".to_string())],
+            spawn_points: vec!["other room".to_string()],
+            ..default()
+        };
+        let level = parse_level(
+            "
+            #########
+            #P.o#s.G#
+            #########
+            ",
+            &meta,
+        );
+        assert!(reaches_goal(&level));
+
+        // and without the portal the goal is walled off
+        let level = parse_level(
+            "
+            #########
+            #P..#s.G#
+            #########
+            ",
+            &meta,
+        );
+        assert!(!reaches_goal(&level));
+    }
+}
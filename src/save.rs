@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::level::{CollectibleTally, LevelCompleted};
+use crate::speedrun::{secs_to_steps, Split};
+
+// Bump this whenever the format changes in a way that old saves can't be read
+// as. Saves with a different version are ignored rather than half-understood.
+pub const SAVE_VERSION: u32 = 1;
+
+// under the user's data directory, eg ~/.local/share on linux
+const SAVE_DIR: &str = "jump-rectangle";
+const SAVE_FILE: &str = "save.ron";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LevelProgress {
+    pub completed: bool,
+    // in physics steps
+    pub best_steps: Option<u32>,
+    // the splits of the run that set best_steps
+    pub best_splits: Vec<Split>,
+    // in seconds, from saves written before times were counted in physics
+    // steps. Converted into best_steps when the save is loaded, and never
+    // written back.
+    #[serde(skip_serializing)]
+    best_time: Option<f32>,
+    // the most collectibles picked up in a single completed run
+    pub best_collectibles: u32,
+    pub total_collectibles: u32,
+}
+
+// Everything that persists between runs. Loaded at startup and written
+// whenever a level is completed.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    // keyed by level path, relative to the assets directory
+    pub levels: HashMap<PathBuf, LevelProgress>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            levels: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    NoDataDir,
+    Io(std::io::Error),
+    Serialize(ron::Error),
+}
+
+impl SaveData {
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join(SAVE_DIR).join(SAVE_FILE))
+    }
+
+    // Starts afresh if there's no save yet, or if it can't be read
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            warn!("no data directory, progress won't be saved");
+            return Self::default();
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match ron::from_str::<SaveData>(&contents) {
+            Ok(mut save) if save.version == SAVE_VERSION => {
+                save.upgrade_best_times();
+                save
+            }
+            Ok(save) => {
+                warn!(
+                    "ignoring {} with unsupported version {}",
+                    path.display(),
+                    save.version
+                );
+                Self::default()
+            }
+            Err(e) => {
+                warn!("failed to parse {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    fn upgrade_best_times(&mut self) {
+        for progress in self.levels.values_mut() {
+            let Some(best_time) = progress.best_time.take() else {
+                continue;
+            };
+            if progress.best_steps.is_none() {
+                progress.best_steps = Some(secs_to_steps(best_time));
+            }
+        }
+    }
+
+    pub fn write(&self) -> Result<(), SaveError> {
+        let path = Self::path().ok_or(SaveError::NoDataDir)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(SaveError::Io)?;
+        }
+        let contents = ron::ser::to_string_pretty(self, default())
+            .map_err(SaveError::Serialize)?;
+        std::fs::write(path, contents).map_err(SaveError::Io)
+    }
+
+    pub fn level(&self, level_path: &Path) -> Option<&LevelProgress> {
+        self.levels.get(level_path)
+    }
+
+    pub fn is_completed(&self, level_path: &Path) -> bool {
+        self.level(level_path)
+            .is_some_and(|progress| progress.completed)
+    }
+
+    pub fn record_completion(
+        &mut self,
+        level_path: &Path,
+        steps: u32,
+        splits: &[Split],
+        collectibles: CollectibleTally,
+    ) {
+        let progress = self.levels.entry(level_path.to_path_buf()).or_default();
+        progress.completed = true;
+        if progress.best_steps.map_or(true, |best| steps < best) {
+            progress.best_steps = Some(steps);
+            progress.best_splits = splits.to_vec();
+        }
+        progress.best_collectibles =
+            progress.best_collectibles.max(collectibles.collected);
+        progress.total_collectibles = collectibles.total;
+    }
+}
+
+pub fn save_on_level_completed(
+    mut level_completed: EventReader<LevelCompleted>,
+    mut save: ResMut<SaveData>,
+) {
+    let mut changed = false;
+    for LevelCompleted {
+        level_path,
+        steps,
+        splits,
+        collectibles,
+    } in level_completed.iter()
+    {
+        save.record_completion(level_path, *steps, splits, *collectibles);
+        changed = true;
+    }
+    if !changed {
+        return;
+    }
+    if let Err(e) = save.write() {
+        warn!("failed to write save file: {:?}", e);
+    }
+}
@@ -0,0 +1,218 @@
+#![allow(clippy::type_complexity)]
+
+use bevy::prelude::*;
+use iyes_loopless::state::NextState;
+use leafwing_input_manager::prelude::*;
+
+use crate::bindings::{Bindings, Control};
+use crate::input::GlobalAction;
+use crate::menu::{
+    menu_node, menu_screen_node, Menu, MenuConfirmed, MenuItemBundle,
+};
+use crate::platformer::{AppState, GameFont};
+use crate::state_transitions::despawn_where;
+
+// Where to go when leaving the settings screen, since it can be reached from
+// both the main menu and the pause menu
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct SettingsReturnTo(pub AppState);
+
+// Exists while we're waiting for the player to press the new key or button for
+// a control. The menu doesn't respond to input in the meantime.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Rebinding(pub Control);
+
+#[derive(Component)]
+pub struct SettingsScreen;
+
+// Shows what's going on with rebinding, eg conflicts
+#[derive(Component)]
+pub struct SettingsStatus;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub enum SettingsOption {
+    Rebind(Control),
+    ResetDefaults,
+    Back,
+}
+
+// small enough to fit every control on screen
+const ITEM_FONT_SIZE: f32 = 14.0;
+
+fn binding_label(control: Control, bindings: &Bindings) -> String {
+    let key = bindings
+        .keyboard
+        .get(&control)
+        .map_or("-".to_string(), |key| format!("{key:?}"));
+    let button = bindings
+        .gamepad
+        .get(&control)
+        .map_or("-".to_string(), |button| format!("{button:?}"));
+    format!("{:<10} {key:>10} {button:>10}", control.name())
+}
+
+pub fn enter_settings(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    bindings: Res<Bindings>,
+) {
+    let heading_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 30.0,
+        color: Color::BLACK,
+    };
+    let status_style = TextStyle {
+        font_size: ITEM_FONT_SIZE,
+        ..heading_style.clone()
+    };
+
+    commands
+        .spawn(menu_screen_node(Color::rgba(1.0, 1.0, 1.0, 0.8)))
+        .insert(SettingsScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("SETTINGS", heading_style));
+            parent
+                .spawn(TextBundle::from_section(
+                    "choose a control to rebind it",
+                    status_style,
+                ))
+                .insert(SettingsStatus);
+            parent
+                .spawn(menu_node())
+                .insert(Menu::default())
+                .with_children(|menu| {
+                    for control in Control::ALL {
+                        menu.spawn(
+                            MenuItemBundle::new(
+                                binding_label(control, &bindings),
+                                &font.0,
+                            )
+                            .with_font_size(ITEM_FONT_SIZE),
+                        )
+                        .insert(SettingsOption::Rebind(control));
+                    }
+                    menu.spawn(
+                        MenuItemBundle::new("reset to defaults", &font.0)
+                            .with_font_size(ITEM_FONT_SIZE),
+                    )
+                    .insert(SettingsOption::ResetDefaults);
+                    menu.spawn(
+                        MenuItemBundle::new("back", &font.0)
+                            .with_font_size(ITEM_FONT_SIZE),
+                    )
+                    .insert(SettingsOption::Back);
+                });
+        });
+}
+
+pub fn settings_menu_system(
+    global_action_state: Res<ActionState<GlobalAction>>,
+    mut confirmed: EventReader<MenuConfirmed>,
+    option_query: Query<&SettingsOption>,
+    mut status_query: Query<&mut Text, With<SettingsStatus>>,
+    mut bindings: ResMut<Bindings>,
+    return_to: Res<SettingsReturnTo>,
+    mut commands: Commands,
+) {
+    if global_action_state.just_pressed(GlobalAction::Back) {
+        commands.insert_resource(NextState(return_to.0));
+        return;
+    }
+
+    for MenuConfirmed(item) in confirmed.iter() {
+        let Ok(option) = option_query.get(*item) else {
+            continue;
+        };
+        match option {
+            SettingsOption::Rebind(control) => {
+                commands.insert_resource(Rebinding(*control));
+                if let Ok(mut status) = status_query.get_single_mut() {
+                    status.sections[0].value =
+                        format!("press a key or button for {}", control.name());
+                }
+            }
+            SettingsOption::ResetDefaults => {
+                *bindings = Bindings::default();
+                if let Err(e) = bindings.write() {
+                    warn!("failed to write bindings: {:?}", e);
+                }
+            }
+            SettingsOption::Back => commands.insert_resource(NextState(return_to.0)),
+        }
+    }
+}
+
+// Waits for the next key or gamepad button press and binds it to the control
+// being rebound, unless something else is already using it. Escape, or whatever
+// back is bound to, cancels instead, so those can't be bound.
+pub fn rebinding_system(
+    rebinding: Res<Rebinding>,
+    keys: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut status_query: Query<&mut Text, With<SettingsStatus>>,
+    mut bindings: ResMut<Bindings>,
+    mut commands: Commands,
+) {
+    let Rebinding(control) = *rebinding;
+    let key = keys.get_just_pressed().next().copied();
+    let button = gamepad_buttons
+        .get_just_pressed()
+        .next()
+        .map(|button| button.button_type);
+
+    let cancelled = key.is_some_and(|key| {
+        key == KeyCode::Escape || bindings.keyboard.get(&Control::Back) == Some(&key)
+    }) || button
+        .is_some_and(|button| bindings.gamepad.get(&Control::Back) == Some(&button));
+    if cancelled {
+        if let Ok(mut text) = status_query.get_single_mut() {
+            text.sections[0].value = format!("kept {}'s binding", control.name());
+        }
+        commands.remove_resource::<Rebinding>();
+        return;
+    }
+
+    let (result, input_name) = if let Some(key) = key {
+        (bindings.bind_key(control, key), format!("{key:?}"))
+    } else if let Some(button) = button {
+        (bindings.bind_button(control, button), format!("{button:?}"))
+    } else {
+        return;
+    };
+
+    let status = match result {
+        Ok(()) => {
+            if let Err(e) = bindings.write() {
+                warn!("failed to write bindings: {:?}", e);
+            }
+            format!("{} is now {input_name}", control.name())
+        }
+        Err(other) => format!("{input_name} is already used for {}", other.name()),
+    };
+    if let Ok(mut text) = status_query.get_single_mut() {
+        text.sections[0].value = status;
+    }
+    commands.remove_resource::<Rebinding>();
+}
+
+pub fn settings_labels_system(
+    bindings: Res<Bindings>,
+    mut item_query: Query<(&SettingsOption, &mut Text)>,
+) {
+    if !bindings.is_changed() {
+        return;
+    }
+    for (option, mut text) in item_query.iter_mut() {
+        if let SettingsOption::Rebind(control) = option {
+            text.sections[0].value = binding_label(*control, &bindings);
+        }
+    }
+}
+
+pub fn exit_settings(
+    to_despawn: Query<Entity, With<SettingsScreen>>,
+    mut commands: Commands,
+) {
+    commands.remove_resource::<Rebinding>();
+    despawn_where(to_despawn, commands);
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::platformer::PHYSICS_TIME_STEP;
+use crate::save::SaveData;
+
+// The time a checkpoint was first reached in a run. Splits are compared by
+// checkpoint rather than by the order they were reached in, since checkpoints
+// can be taken in a different order or skipped.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Split {
+    // the checkpoint's position in the level's tile grid
+    pub checkpoint: IVec2,
+    // in physics steps
+    pub steps: u32,
+}
+
+// How long the current level has been played for, counted in physics steps
+// rather than wall-clock time so that it comes out the same however the frames
+// fall, and so time spent paused isn't counted. Reset whenever a level is
+// spawned.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct LevelTimer {
+    pub steps: u32,
+    // in the order the checkpoints were first reached
+    pub splits: Vec<Split>,
+}
+
+// The player's best run of the level being played, as it was when the level was
+// loaded, so the current run can be compared against it
+#[derive(Resource, Default, Clone, Debug)]
+pub struct PersonalBest {
+    pub steps: Option<u32>,
+    pub splits: Vec<Split>,
+}
+
+impl PersonalBest {
+    pub fn for_level(save: &SaveData, level_path: &Path) -> Self {
+        match save.level(level_path) {
+            Some(progress) => PersonalBest {
+                steps: progress.best_steps,
+                splits: progress.best_splits.clone(),
+            },
+            None => PersonalBest::default(),
+        }
+    }
+
+    // The best run's time at the checkpoint, if it reached it
+    pub fn split_at(&self, checkpoint: IVec2) -> Option<u32> {
+        self.splits
+            .iter()
+            .find(|split| split.checkpoint == checkpoint)
+            .map(|split| split.steps)
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct SpeedrunConfig {
+    // show each split on the HUD as checkpoints are reached
+    pub show_splits: bool,
+}
+
+impl Default for SpeedrunConfig {
+    fn default() -> Self {
+        SpeedrunConfig { show_splits: true }
+    }
+}
+
+pub fn steps_to_secs(steps: u32) -> f32 {
+    steps as f32 * PHYSICS_TIME_STEP
+}
+
+pub fn secs_to_steps(secs: f32) -> u32 {
+    (secs / PHYSICS_TIME_STEP).round() as u32
+}
+
+// eg 12.35, or 1:02.35 once it's over a minute
+pub fn format_time(steps: u32) -> String {
+    let secs = steps_to_secs(steps);
+    let minutes = (secs / 60.0).floor();
+    if minutes > 0.0 {
+        format!("{minutes}:{:05.2}", secs - minutes * 60.0)
+    } else {
+        format!("{secs:.2}")
+    }
+}
+
+// How far ahead (-) or behind (+) of the personal best a time is
+pub fn format_delta(steps: u32, best: u32) -> String {
+    let delta = steps_to_secs(steps) - steps_to_secs(best);
+    format!("{delta:+.2}")
+}
+
+// Runs every physics step while in game, including while the guy is dying, so
+// deaths cost time
+pub fn level_timer_system(mut level_timer: ResMut<LevelTimer>) {
+    level_timer.steps += 1;
+}
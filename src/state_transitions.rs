@@ -1,25 +1,314 @@
 #![allow(clippy::type_complexity)]
-use crate::guy::Guy;
+use crate::bindings::Bindings;
+use crate::death::{DeathCount, PlayState};
+use crate::guy::{Guy, RespawnGuy};
+use crate::input::GlobalAction;
 use crate::level::*;
-use crate::platformer::{spawn_level, AppState, PauseMessage};
+use crate::level_meta::{CurrentLevelMeta, LevelMeta, LevelMusic};
+use crate::level_pack::{LevelPack, LevelPackHandle, LEVEL_PACK_PATH};
+use crate::lives::{refill_lives, LivesConfig};
+use crate::menu::{
+    menu_node, menu_screen_node, Menu, MenuConfirmed, MenuInputDelay, MenuItemBundle,
+};
+use crate::platformer::{spawn_level, AppState, GameFont, DEFAULT_CLEAR_COLOR};
+use crate::save::SaveData;
+use crate::settings::SettingsReturnTo;
+use crate::speedrun::{
+    format_delta, format_time, secs_to_steps, LevelTimer, PersonalBest,
+};
+use crate::transition::{load_level, transition_to, LevelRequest};
+use bevy::app::AppExit;
+use bevy::asset::LoadState;
 use bevy::ecs::query::ReadOnlyWorldQuery;
 use bevy::prelude::*;
 use iyes_loopless::state::NextState;
+use leafwing_input_manager::prelude::*;
+use std::path::{Path, PathBuf};
+
+#[derive(Component)]
+pub struct PauseScreen;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub enum PauseOption {
+    Resume,
+    // start the level again from the beginning
+    Restart,
+    ReturnToOverworld,
+    Settings,
+    Quit,
+}
 
 pub fn enter_paused(
-    mut pause_message_vis: Query<&mut Visibility, With<PauseMessage>>,
+    mut commands: Commands,
+    font: Res<GameFont>,
+    loading_level: Res<LoadingLevel>,
 ) {
     debug!("Game paused");
-    let mut pm_visibility = pause_message_vis.single_mut();
-    pm_visibility.is_visible = true;
+    let heading_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 100.0,
+        color: Color::BLACK,
+    };
+    let in_overworld = *loading_level == LoadingLevel::Overworld;
+
+    commands
+        .spawn(menu_screen_node(Color::rgba(1.0, 1.0, 1.0, 0.5)))
+        .insert(PauseScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("PAUSED", heading_style));
+            parent
+                .spawn(menu_node())
+                // start unpauses
+                .insert(Menu {
+                    ignore_start: true,
+                    ..default()
+                })
+                .with_children(|menu| {
+                    menu.spawn(MenuItemBundle::new("resume", &font.0))
+                        .insert(PauseOption::Resume);
+                    menu.spawn(MenuItemBundle::new("restart level", &font.0))
+                        .insert(PauseOption::Restart);
+                    let return_item = if in_overworld {
+                        MenuItemBundle::disabled("return to overworld", &font.0)
+                    } else {
+                        MenuItemBundle::new("return to overworld", &font.0)
+                    };
+                    menu.spawn(return_item)
+                        .insert(PauseOption::ReturnToOverworld);
+                    menu.spawn(MenuItemBundle::new("settings", &font.0))
+                        .insert(PauseOption::Settings);
+                    menu.spawn(MenuItemBundle::new("quit", &font.0))
+                        .insert(PauseOption::Quit);
+                });
+        });
+}
+
+pub fn pause_menu_system(
+    global_action_state: Res<ActionState<GlobalAction>>,
+    mut confirmed: EventReader<MenuConfirmed>,
+    option_query: Query<&PauseOption>,
+    mut exit: EventWriter<AppExit>,
+    mut commands: Commands,
+) {
+    if global_action_state.just_pressed(GlobalAction::Back) {
+        commands.insert_resource(NextState(AppState::InGame));
+        return;
+    }
+
+    for MenuConfirmed(item) in confirmed.iter() {
+        let Ok(option) = option_query.get(*item) else {
+            continue;
+        };
+        match option {
+            PauseOption::Resume => {
+                commands.insert_resource(NextState(AppState::InGame))
+            }
+            // LoadingLevel still holds the level we're in, and entering Loading
+            // despawns its contents
+            PauseOption::Restart => transition_to(&mut commands, AppState::Loading),
+            PauseOption::ReturnToOverworld => return_to_overworld(&mut commands),
+            PauseOption::Settings => {
+                commands.insert_resource(SettingsReturnTo(AppState::Paused));
+                commands.insert_resource(NextState(AppState::Settings));
+            }
+            PauseOption::Quit => exit.send(AppExit),
+        }
+    }
 }
 
 pub fn exit_paused(
-    mut pause_message_vis: Query<&mut Visibility, With<PauseMessage>>,
+    to_despawn: Query<Entity, With<PauseScreen>>,
+    commands: Commands,
 ) {
     debug!("Game resumed");
-    let mut pm_visibility = pause_message_vis.single_mut();
-    pm_visibility.is_visible = false;
+    despawn_where(to_despawn, commands);
+}
+
+#[derive(Component)]
+pub struct ResultsScreen;
+
+// The results screen moves on by itself after this long, if the player doesn't
+// press start first
+#[derive(Resource)]
+pub struct ResultsTimer(Timer);
+
+impl ResultsTimer {
+    const RESULTS_DURATION: f32 = 3.0;
+}
+
+pub fn enter_level_complete(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    level_meta: Res<CurrentLevelMeta>,
+    loading_level: Res<LoadingLevel>,
+    level_timer: Res<LevelTimer>,
+    personal_best: Res<PersonalBest>,
+) {
+    debug!("Level complete");
+    commands.insert_resource(ResultsTimer(Timer::from_seconds(
+        ResultsTimer::RESULTS_DURATION,
+        TimerMode::Once,
+    )));
+
+    let CurrentLevelMeta(meta) = &*level_meta;
+    let level_name = match (&*loading_level, &meta.author) {
+        (LoadingLevel::Path(level_path), Some(author)) => {
+            format!("{} by {author}", meta.display_name(level_path))
+        }
+        (LoadingLevel::Path(level_path), None) => meta.display_name(level_path),
+        (LoadingLevel::Overworld, _) => String::new(),
+    };
+    let par_message = match meta.par_time.map(secs_to_steps) {
+        Some(par) => format!(
+            "par {} ({})",
+            format_time(par),
+            format_delta(level_timer.steps, par)
+        ),
+        None => String::new(),
+    };
+    // the overworld doesn't keep times
+    let best_message = match (&*loading_level, personal_best.steps) {
+        (LoadingLevel::Overworld, _) => String::new(),
+        (LoadingLevel::Path(_), Some(best)) if best <= level_timer.steps => {
+            format!(
+                "best {} ({})",
+                format_time(best),
+                format_delta(level_timer.steps, best)
+            )
+        }
+        (LoadingLevel::Path(_), Some(best)) => format!(
+            "new personal best! ({})",
+            format_delta(level_timer.steps, best)
+        ),
+        (LoadingLevel::Path(_), None) => "new personal best!".to_string(),
+    };
+    let heading_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 50.0,
+        color: Color::BLACK,
+    };
+    let body_style = TextStyle {
+        font_size: 20.0,
+        ..heading_style.clone()
+    };
+
+    commands
+        .spawn(menu_screen_node(Color::rgba(1.0, 1.0, 1.0, 0.5)))
+        .insert(ResultsScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("LEVEL COMPLETE", heading_style));
+            parent.spawn(TextBundle::from_section(level_name, body_style.clone()));
+            parent.spawn(TextBundle::from_section(
+                format!("time {}", format_time(level_timer.steps)),
+                body_style.clone(),
+            ));
+            parent.spawn(TextBundle::from_section(par_message, body_style.clone()));
+            parent.spawn(TextBundle::from_section(best_message, body_style.clone()));
+            parent.spawn(TextBundle::from_section(
+                "press start to continue",
+                body_style,
+            ));
+        });
+}
+
+pub fn results_screen_system(
+    time: Res<Time>,
+    mut results_timer: ResMut<ResultsTimer>,
+    mut commands: Commands,
+) {
+    if results_timer.0.tick(time.delta()).just_finished() {
+        return_to_overworld(&mut commands);
+    }
+}
+
+pub fn exit_level_complete(
+    to_despawn: Query<Entity, With<ResultsScreen>>,
+    mut commands: Commands,
+) {
+    commands.remove_resource::<ResultsTimer>();
+    despawn_where(to_despawn, commands);
+}
+
+#[derive(Component)]
+pub struct GameOverScreen;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub enum GameOverOption {
+    // carry on from the last checkpoint
+    Continue,
+    // start the level again from the beginning
+    Retry,
+    BackToOverworld,
+}
+
+// Confirm is on the same button as jump by default, so a jump pressed as the
+// guy dies would otherwise pick whatever the game over menu starts on
+const GAME_OVER_INPUT_DELAY: f32 = 0.75;
+
+pub fn enter_game_over(mut commands: Commands, font: Res<GameFont>) {
+    debug!("Game over");
+    let heading_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 50.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn(menu_screen_node(Color::rgba(1.0, 1.0, 1.0, 0.5)))
+        .insert(GameOverScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("GAME OVER", heading_style));
+            parent
+                .spawn(menu_node())
+                .insert(Menu::default())
+                .insert(MenuInputDelay::from_seconds(GAME_OVER_INPUT_DELAY))
+                .with_children(|menu| {
+                    for (label, option) in [
+                        ("continue", GameOverOption::Continue),
+                        ("retry", GameOverOption::Retry),
+                        ("back to overworld", GameOverOption::BackToOverworld),
+                    ] {
+                        menu.spawn(MenuItemBundle::new(label, &font.0))
+                            .insert(option);
+                    }
+                });
+        });
+}
+
+// Whatever's chosen, the guy gets his lives back
+pub fn game_over_menu_system(
+    mut confirmed: EventReader<MenuConfirmed>,
+    option_query: Query<&GameOverOption>,
+    lives_config: Res<LivesConfig>,
+    mut respawn: EventWriter<RespawnGuy>,
+    mut commands: Commands,
+) {
+    for MenuConfirmed(item) in confirmed.iter() {
+        let Ok(option) = option_query.get(*item) else {
+            continue;
+        };
+        refill_lives(&mut commands, &lives_config);
+        match option {
+            GameOverOption::Continue => {
+                respawn.send(RespawnGuy);
+                commands.insert_resource(NextState(AppState::InGame));
+            }
+            // LoadingLevel still holds the level we're in
+            GameOverOption::Retry => transition_to(&mut commands, AppState::Loading),
+            GameOverOption::BackToOverworld => return_to_overworld(&mut commands),
+        }
+    }
+}
+
+pub fn exit_game_over(
+    to_despawn: Query<Entity, With<GameOverScreen>>,
+    commands: Commands,
+) {
+    despawn_where(to_despawn, commands);
+}
+
+pub fn return_to_overworld(commands: &mut Commands) {
+    load_level(commands, LevelRequest::new(LoadingLevel::Overworld));
 }
 
 pub fn despawn_where<F: ReadOnlyWorldQuery>(
@@ -27,12 +316,26 @@ pub fn despawn_where<F: ReadOnlyWorldQuery>(
     mut commands: Commands,
 ) {
     to_despawn.for_each(|e| {
-        commands.entity(e).despawn();
+        commands.entity(e).despawn_recursive();
     });
 }
 
 pub fn despawn_level_contents(
-    to_despawn: Query<Entity, Or<(With<Guy>, With<Wall>, With<Portal>)>>,
+    to_despawn: Query<
+        Entity,
+        Or<(
+            With<Guy>,
+            With<Wall>,
+            With<Portal>,
+            With<SpawnPoint>,
+            With<Goal>,
+            With<Collectible>,
+            With<Checkpoint>,
+            With<ExtraLife>,
+            With<Hazard>,
+            With<DecorationSprite>,
+        )>,
+    >,
     commands: Commands,
 ) {
     despawn_where(to_despawn, commands)
@@ -55,81 +358,442 @@ pub fn despawn_level_contents(
 // 2. enter_loading checks whether we want a normal level, or the overworld level.
 //    - Normal levels will be loaded as png files via the AssetServer. We
 //      convert them into `RgbaImage`, then generate a `Level` from the pixels.
-//    - The overworld will be dynamically generated without an image file, based on
-//      how many regular levels there are. (TODO I just realized this check
-//      depends on a relative path to the assets directory)
+//    - The overworld is loaded the same way if the level pack has an authored
+//      one. Otherwise it will be dynamically generated without an image file,
+//      with a portal for each level listed in the level pack manifest.
 //  3. If we want to load a regular level
-//     a. we insert a LoadingLevelImageHandle
-//        resource, and wait in Loading using `wait_level_load` to check whether
-//        the Image is available yet. Once it is, we generate a Level from it,
-//        insert a LoadedLevel, and transition to AppState::InGame.
-//     b. this will trigger exit_loading, which looks at the LoadedLevel, and
-//        spawns entities based on it.
+//     a. we insert LoadingLevelImageHandle, LoadingLevelMetaHandle and
+//        LoadingLevelLayerHandles resources, and wait in Loading using
+//        `wait_level_load` to check whether the Image is available yet, and
+//        whether the metadata and layers have each either loaded or failed to
+//        (meaning there aren't any). Once they're ready, we
+//        generate a Level from the image, insert a LoadedLevel, and transition
+//        to AppState::InGame.
+//     b. this will trigger exit_loading, which looks at the LoadedLevel, applies
+//        the settings from its metadata, and spawns entities based on it.
 //
-//     Otherwise, if we want the overworld level, we generate it immediately in
-//     enter_loading, insert the LoadedLevel, and directly transition to AppState::InGame,
-//     thereby triggering exit_loading, which will spawn the entities.
-//     This should mean wait_level_load is never called.
+//     Otherwise, if we want the overworld level, `wait_overworld_load` waits
+//     for the LevelPack (loaded once in `setup`) to be available. If the pack
+//     has an authored overworld, it inserts the same resources as in 3a and
+//     carries on from there. If not, it generates the level from the pack,
+//     inserts the LoadedLevel, and transitions to AppState::InGame, thereby
+//     triggering exit_loading, which will spawn the entities.
+//     wait_level_load only runs while there's a LoadingLevelImageHandle, and
+//     wait_overworld_load only while there isn't.
+// 4. If the level image or level pack fails to load, or the level fails to
+//    parse, a LoadingFailed is inserted instead. Neither wait system runs while
+//    it exists, and the loading screen shows it until the player backs out
+//    (loading_failed_system), which goes through exit_loading without a
+//    LoadedLevel.
 //
 // This approach feels insanely brittle
 #[derive(Resource)]
-pub struct LoadedLevel(Level);
+pub struct LoadedLevel(Level, LevelMeta);
 
 #[derive(Resource)]
 pub struct LoadingLevelImageHandle(Handle<Image>);
 
+#[derive(Resource)]
+pub struct LoadingLevelMetaHandle(Handle<LevelMeta>);
+
+#[derive(Resource)]
+pub struct LoadingLevelLayerHandles(Vec<(DecorationLayer, Handle<Image>)>);
+
+// Why the level couldn't be loaded. While this exists we stop trying, and the
+// loading screen shows it until the player backs out
+#[derive(Resource, Debug)]
+pub enum LoadingFailed {
+    LevelPack,
+    LevelImage(PathBuf),
+    Parse(LevelParseError),
+}
+
+impl LoadingFailed {
+    fn message(&self) -> String {
+        match self {
+            LoadingFailed::LevelPack => format!("couldn't load {LEVEL_PACK_PATH}"),
+            LoadingFailed::LevelImage(path) => {
+                format!("couldn't load {}", path.display())
+            }
+            LoadingFailed::Parse(e) => format!("couldn't parse the level: {e:?}"),
+        }
+    }
+}
+
+// The state we were in when we started loading, so the loading screen knows
+// where to go back to. Inserted by transition_system as it switches to
+// AppState::Loading, and left alone when loading starts again from Loading.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LoadingFrom(pub AppState);
+
 pub fn enter_loading(
     mut commands: Commands,
     to_load: Res<LoadingLevel>,
     asset_server: Res<AssetServer>,
 ) {
     info!("enter_loading");
+    commands.remove_resource::<LoadedLevel>();
+    commands.remove_resource::<LoadingFailed>();
     match &*to_load {
         LoadingLevel::Path(level_path) => {
-            let level_image: Handle<Image> = asset_server.load(level_path.as_path());
-            commands.insert_resource(LoadingLevelImageHandle(level_image));
+            start_loading_level_image(&mut commands, &asset_server, level_path);
         }
-        LoadingLevel::Overworld => {
-            let level = Level::generate_overworld_level();
-            commands.insert_resource(LoadedLevel(level));
-            commands.insert_resource(NextState(AppState::InGame));
+        // we need the level pack to know whether there's an authored
+        // overworld, see wait_overworld_load
+        LoadingLevel::Overworld => (),
+    }
+}
+
+// Starts loading a level image and everything that goes with it, to be waited
+// on by wait_level_load
+fn start_loading_level_image(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    level_path: &Path,
+) {
+    let level_image: Handle<Image> = asset_server.load(level_path);
+    commands.insert_resource(LoadingLevelImageHandle(level_image));
+    let level_meta: Handle<LevelMeta> =
+        asset_server.load(LevelMeta::sidecar_path(level_path));
+    commands.insert_resource(LoadingLevelMetaHandle(level_meta));
+    let layer_images = DecorationLayer::ALL
+        .into_iter()
+        .map(|layer| (layer, asset_server.load(layer.image_path(level_path))))
+        .collect();
+    commands.insert_resource(LoadingLevelLayerHandles(layer_images));
+}
+
+pub fn wait_overworld_load(
+    level_pack_handle: Res<LevelPackHandle>,
+    level_packs: Res<Assets<LevelPack>>,
+    asset_server: Res<AssetServer>,
+    save: Res<SaveData>,
+    return_portal: Option<Res<ReturnPortal>>,
+    mut commands: Commands,
+) {
+    let LevelPackHandle(handle) = &*level_pack_handle;
+    let Some(level_pack) = level_packs.get(handle) else {
+        if asset_server.get_load_state(handle) == LoadState::Failed {
+            commands.insert_resource(LoadingFailed::LevelPack);
         }
+        debug!("waiting for level pack to become available");
+        return;
+    };
+
+    // an authored overworld is loaded like any other level
+    if let Some(overworld_path) = &level_pack.overworld {
+        start_loading_level_image(&mut commands, &asset_server, overworld_path);
+        return;
     }
+
+    let level = Level::generate_overworld_level(
+        level_pack,
+        &save,
+        return_portal
+            .as_deref()
+            .map(|ReturnPortal(level_path)| level_path.as_path()),
+    );
+    let meta = LevelMeta {
+        name: Some("Overworld".to_string()),
+        ..default()
+    };
+    commands.insert_resource(LoadedLevel(level, meta));
+    commands.insert_resource(NextState(AppState::InGame));
 }
 
-// This should only run if we're loading a regular level, which implies waiting
-// on the AssetServer. If we want the overworld level, we should have skipped through
-// AppState::Loading to AppState::InGame
+// This should only run if we're loading a level image, either a regular level
+// or an authored overworld. Otherwise wait_overworld_load generates the
+// overworld
+#[allow(clippy::too_many_arguments)]
 pub fn wait_level_load(
     level_image_handle: Res<LoadingLevelImageHandle>,
+    level_meta_handle: Res<LoadingLevelMetaHandle>,
+    level_layer_handles: Res<LoadingLevelLayerHandles>,
     images: Res<Assets<Image>>,
+    level_metas: Res<Assets<LevelMeta>>,
+    level_pack_handle: Res<LevelPackHandle>,
+    level_packs: Res<Assets<LevelPack>>,
+    asset_server: Res<AssetServer>,
+    save: Res<SaveData>,
+    loading_level: Res<LoadingLevel>,
+    return_portal: Option<Res<ReturnPortal>>,
     mut commands: Commands,
 ) {
     info!("wait_level_load");
     let LoadingLevelImageHandle(handle) = &*level_image_handle;
     let Some(img) = images.get(&handle) else {
+        if asset_server.get_load_state(handle) == LoadState::Failed {
+            let path = asset_server
+                .get_handle_path(handle)
+                .map(|path| path.path().to_path_buf())
+                .unwrap_or_default();
+            commands.insert_resource(LoadingFailed::LevelImage(path));
+        }
         debug!("waiting for level image to become available");
         return
     };
 
-    let level = Level::from_bevy_image(img).unwrap_or_else(|e| match e {
-        LevelParseError::WrongNumberPlayers(_) => {
-            panic!("Wrong number of players while parsing level image: {:?}", e)
+    // The sidecar file is optional, so failing to load it just means the level
+    // doesn't have one
+    let LoadingLevelMetaHandle(meta_handle) = &*level_meta_handle;
+    let mut meta = match asset_server.get_load_state(meta_handle) {
+        LoadState::Loaded => {
+            level_metas.get(meta_handle).cloned().unwrap_or_default()
+        }
+        LoadState::Failed => LevelMeta::default(),
+        _ => {
+            debug!("waiting for level metadata to become available");
+            return;
+        }
+    };
+
+    // Decoration layers are optional too
+    let LoadingLevelLayerHandles(layer_handles) = &*level_layer_handles;
+    let mut layer_images = Vec::new();
+    for (layer, layer_handle) in layer_handles {
+        match asset_server.get_load_state(layer_handle) {
+            LoadState::Loaded => layer_images.extend(
+                images
+                    .get(layer_handle)
+                    .map(|layer_image| (*layer, layer_image)),
+            ),
+            LoadState::Failed => (),
+            _ => {
+                debug!("waiting for decoration layers to become available");
+                return;
+            }
         }
+    }
+
+    // needed to resolve level ids and open gates
+    let Some(level_pack) = level_packs.get(&level_pack_handle.0) else {
+        if asset_server.get_load_state(&level_pack_handle.0) == LoadState::Failed {
+            commands.insert_resource(LoadingFailed::LevelPack);
+        }
+        debug!("waiting for level pack to become available");
+        return;
+    };
+
+    let parsed = Level::from_bevy_image(img, &meta).and_then(|mut level| {
+        level.resolve_level_ids(level_pack)?;
+        Ok(level)
     });
-    commands.insert_resource(LoadedLevel(level));
+    let mut level = match parsed {
+        Ok(level) => level,
+        Err(e) => {
+            warn!("failed to parse level image: {:?}", e);
+            commands.insert_resource(LoadingFailed::Parse(e));
+            return;
+        }
+    };
+    level.open_gates(level_pack, &save);
+    // like a layer that's missing, one that can't be read is left out rather
+    // than failing the whole level
+    for (layer, layer_image) in layer_images {
+        if let Err(e) =
+            level.add_decoration_layer_from_bevy_image(layer_image, layer)
+        {
+            warn!("skipping {:?} decoration layer: {:?}", layer, e);
+        }
+    }
+
+    if let LoadingLevel::Overworld = *loading_level {
+        if let Some(ReturnPortal(level_path)) = return_portal.as_deref() {
+            level.place_player_by_portal(level_path);
+        }
+        meta.name.get_or_insert_with(|| "Overworld".to_string());
+    }
+    commands.insert_resource(LoadedLevel(level, meta));
     commands.insert_resource(NextState(AppState::InGame));
 }
 
+#[derive(Component)]
+pub struct LoadingScreen;
+
+// Everything on the loading screen under its heading
+#[derive(Component)]
+pub struct LoadingStatus;
+
+pub fn spawn_loading_screen(mut commands: Commands, font: Res<GameFont>) {
+    let heading_style = TextStyle {
+        font: font.0.clone(),
+        font_size: 50.0,
+        color: Color::BLACK,
+    };
+    let body_style = TextStyle {
+        font_size: 20.0,
+        ..heading_style.clone()
+    };
+
+    commands
+        .spawn(menu_screen_node(Color::NONE))
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("LOADING", heading_style));
+            parent
+                .spawn(
+                    TextBundle::from_section("", body_style)
+                        .with_text_alignment(TextAlignment::CENTER),
+                )
+                .insert(LoadingStatus);
+        });
+}
+
+fn load_state_name(load_state: LoadState) -> &'static str {
+    match load_state {
+        LoadState::NotLoaded => "not loaded",
+        LoadState::Loading => "loading",
+        LoadState::Loaded => "loaded",
+        LoadState::Failed => "failed",
+        LoadState::Unloaded => "unloaded",
+    }
+}
+
+// Shows which level is loading, how far along each of its assets are, and what
+// went wrong if loading failed
+#[allow(clippy::too_many_arguments)]
+pub fn loading_screen_system(
+    loading_level: Res<LoadingLevel>,
+    level_pack_handle: Res<LevelPackHandle>,
+    level_packs: Res<Assets<LevelPack>>,
+    level_image_handle: Option<Res<LoadingLevelImageHandle>>,
+    level_meta_handle: Option<Res<LoadingLevelMetaHandle>>,
+    level_layer_handles: Option<Res<LoadingLevelLayerHandles>>,
+    loading_failed: Option<Res<LoadingFailed>>,
+    asset_server: Res<AssetServer>,
+    mut status_query: Query<&mut Text, With<LoadingStatus>>,
+) {
+    let Ok(mut text) = status_query.get_single_mut() else {
+        return;
+    };
+
+    let level_pack = level_packs.get(&level_pack_handle.0);
+    let mut lines = vec![match &*loading_level {
+        LoadingLevel::Path(path) => level_pack
+            .and_then(|pack| pack.levels.iter().find(|entry| entry.path == *path))
+            .map_or_else(|| path.display().to_string(), |entry| entry.name.clone()),
+        LoadingLevel::Overworld => "Overworld".to_string(),
+    }];
+    lines.push(String::new());
+    lines.push(format!(
+        "level pack: {}",
+        load_state_name(asset_server.get_load_state(&level_pack_handle.0))
+    ));
+    if let Some(LoadingLevelImageHandle(handle)) = level_image_handle.as_deref() {
+        lines.push(format!(
+            "level image: {}",
+            load_state_name(asset_server.get_load_state(handle))
+        ));
+    }
+    // these are optional, so failing to load is fine
+    if let Some(LoadingLevelMetaHandle(handle)) = level_meta_handle.as_deref() {
+        lines.push(format!(
+            "metadata: {}",
+            load_state_name(asset_server.get_load_state(handle))
+        ));
+    }
+    if let Some(LoadingLevelLayerHandles(handles)) = level_layer_handles.as_deref() {
+        let done = handles
+            .iter()
+            .filter(|(_, handle)| {
+                matches!(
+                    asset_server.get_load_state(handle),
+                    LoadState::Loaded | LoadState::Failed
+                )
+            })
+            .count();
+        lines.push(format!("decoration layers: {done}/{}", handles.len()));
+    }
+    if let Some(loading_failed) = loading_failed.as_deref() {
+        lines.push(String::new());
+        lines.push(loading_failed.message());
+        lines.push("press back to return".to_string());
+    }
+
+    let status = lines.join("\n");
+    // avoid touching the text when nothing's changed, so it isn't laid out
+    // again every frame
+    if text.sections[0].value != status {
+        text.sections[0].value = status;
+    }
+}
+
+// Goes back to the menu we came from, if we came from one. Otherwise the level
+// we were playing has already been despawned, so we go to the overworld, or the
+// main menu if it was the overworld that failed.
+pub fn loading_failed_system(
+    global_action_state: Res<ActionState<GlobalAction>>,
+    loading_from: Option<Res<LoadingFrom>>,
+    loading_level: Res<LoadingLevel>,
+    mut commands: Commands,
+) {
+    if !global_action_state.just_pressed(GlobalAction::Back)
+        && !global_action_state.just_pressed(GlobalAction::Confirm)
+    {
+        return;
+    }
+    match loading_from.map(|loading_from| loading_from.0) {
+        Some(state @ (AppState::MainMenu | AppState::LevelSelect)) => {
+            commands.insert_resource(NextState(state))
+        }
+        _ if *loading_level != LoadingLevel::Overworld => {
+            return_to_overworld(&mut commands)
+        }
+        _ => commands.insert_resource(NextState(AppState::MainMenu)),
+    }
+}
+
+pub fn despawn_loading_screen(
+    to_despawn: Query<Entity, With<LoadingScreen>>,
+    commands: Commands,
+) {
+    despawn_where(to_despawn, commands);
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn exit_loading(
-    loaded_level: Res<LoadedLevel>,
+    loaded_level: Option<Res<LoadedLevel>>,
     mut commands: Commands,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     asset_server: Res<AssetServer>,
+    font: Res<GameFont>,
+    mut clear_color: ResMut<ClearColor>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    level_music: Option<Res<LevelMusic>>,
+    loading_level: Res<LoadingLevel>,
+    target_spawn_point: Option<Res<TargetSpawnPoint>>,
+    save: Res<SaveData>,
+    bindings: Res<Bindings>,
 ) {
     info!("exit_loading");
-    let LoadedLevel(level) = &*loaded_level;
-    let tile_texture_handle = asset_server.load("tiles_packed.png");
+    commands.remove_resource::<LoadingLevelImageHandle>();
+    commands.remove_resource::<LoadingLevelMetaHandle>();
+    commands.remove_resource::<LoadingLevelLayerHandles>();
+    commands.remove_resource::<LoadingFailed>();
+    // we're backing out after the level failed to load
+    let Some(loaded_level) = loaded_level else {
+        return;
+    };
+    let LoadedLevel(level, meta) = &*loaded_level;
+
+    clear_color.0 = meta.clear_color.unwrap_or(DEFAULT_CLEAR_COLOR);
+
+    if let Some(LevelMusic(sink)) = level_music.as_deref() {
+        if let Some(sink) = audio_sinks.get(sink) {
+            sink.stop();
+        }
+    }
+    commands.remove_resource::<LevelMusic>();
+    if let Some(music) = &meta.music {
+        let sink = audio.play_with_settings(
+            asset_server.load(music.as_str()),
+            PlaybackSettings::LOOP,
+        );
+        commands.insert_resource(LevelMusic(audio_sinks.get_handle(&sink)));
+    }
+
+    let tile_texture_handle = asset_server.load(meta.tileset());
     let portal_image_handle: Handle<Image> = asset_server.load("portal.png");
     let tile_texture_atlas =
         TextureAtlas::from_grid(tile_texture_handle, Vec2::new(18.0, 18.0), 20, 9, None, None);
@@ -140,10 +804,30 @@ pub fn exit_loading(
         &mut commands,
         tile_texture_atlas_handle,
         portal_image_handle,
-        &level,
+        &font.0,
+        &save,
+        &bindings,
+        level,
+        target_spawn_point
+            .as_deref()
+            .map(|TargetSpawnPoint(spawn)| spawn.as_str()),
     );
 
-    commands.remove_resource::<LoadingLevelImageHandle>();
+    commands.insert_resource(CurrentLevelMeta(meta.clone()));
+    commands.insert_resource(LevelTimer::default());
+    commands.insert_resource(match &*loading_level {
+        LoadingLevel::Path(level_path) => PersonalBest::for_level(&save, level_path),
+        LoadingLevel::Overworld => PersonalBest::default(),
+    });
+    commands.insert_resource(DeathCount::default());
+    commands.insert_resource(level.bounds());
+    // in case the level was left mid-death
+    commands.insert_resource(NextState(PlayState::Playing));
+    commands.insert_resource(CollectibleTally {
+        collected: 0,
+        total: level.collectible_count(),
+    });
+    commands.remove_resource::<TargetSpawnPoint>();
     commands.remove_resource::<LoadedLevel>();
     commands.insert_resource(NextState(AppState::InGame));
     debug!("loading complete, starting game");
@@ -0,0 +1,271 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::level::{LoadingLevel, ReturnPortal, TargetSpawnPoint};
+use crate::platformer::AppState;
+use crate::state_transitions::{LoadingFailed, LoadingFrom};
+
+// How the screen is covered while switching states
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionStyle {
+    Fade,
+    // a rectangle closing in on the centre of the screen
+    Iris,
+}
+
+impl Default for TransitionStyle {
+    fn default() -> Self {
+        TransitionStyle::Iris
+    }
+}
+
+// The level to load when a transition switches to AppState::Loading. Only
+// applied as the state switches, since until then the level being left is still
+// showing and its LoadingLevel and so on are still in use.
+#[derive(Clone, Debug)]
+pub struct LevelRequest {
+    pub level: LoadingLevel,
+    // a named spawn point to start at, rather than the usual player position
+    pub spawn_point: Option<String>,
+    // set when leaving the overworld through a portal, see ReturnPortal
+    pub return_portal: Option<PathBuf>,
+}
+
+impl LevelRequest {
+    pub fn new(level: LoadingLevel) -> Self {
+        LevelRequest {
+            level,
+            spawn_point: None,
+            return_portal: None,
+        }
+    }
+
+    fn apply(self, commands: &mut Commands) {
+        commands.insert_resource(self.level);
+        match self.spawn_point {
+            Some(spawn) => commands.insert_resource(TargetSpawnPoint(spawn)),
+            None => commands.remove_resource::<TargetSpawnPoint>(),
+        }
+        if let Some(level_path) = self.return_portal {
+            commands.insert_resource(ReturnPortal(level_path));
+        }
+    }
+}
+
+// Asks for the screen to be covered before switching to the state. Picked up by
+// transition_system, which ignores it if a transition is already underway, so
+// it's fine to ask every physics step, eg while the guy is touching a portal.
+#[derive(Resource, Clone, Debug)]
+pub struct TransitionRequest {
+    pub next: AppState,
+    // None to load the same level again, if next is AppState::Loading
+    pub level: Option<LevelRequest>,
+}
+
+// Exists for as long as a transition is running. Input is ignored and the game
+// doesn't run in the meantime.
+#[derive(Resource, Debug)]
+pub enum Transition {
+    // covering the screen, then switching state
+    Out {
+        next: AppState,
+        level: Option<LevelRequest>,
+        timer: Timer,
+    },
+    // staying covered while the level loads
+    Hold,
+    // uncovering the screen
+    In {
+        timer: Timer,
+    },
+}
+
+impl Transition {
+    const DURATION: f32 = 0.25;
+
+    // how much of the screen is covered, from 0 to 1
+    fn coverage(&self) -> f32 {
+        match self {
+            Transition::Out { timer, .. } => timer.percent(),
+            Transition::Hold => 1.0,
+            Transition::In { timer } => timer.percent_left(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TransitionOverlay;
+
+// One of the four sides of the iris, each of which covers up to half the screen
+#[derive(Component, Clone, Copy, Debug)]
+pub enum IrisSide {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+pub fn transition_to(commands: &mut Commands, next: AppState) {
+    commands.insert_resource(TransitionRequest { next, level: None });
+}
+
+// Covers the screen, then loads the level
+pub fn load_level(commands: &mut Commands, level: LevelRequest) {
+    commands.insert_resource(TransitionRequest {
+        next: AppState::Loading,
+        level: Some(level),
+    });
+}
+
+pub fn spawn_transition_overlay(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            // above all the other UI
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        })
+        .insert(TransitionOverlay)
+        .with_children(|parent| {
+            for side in [
+                IrisSide::Top,
+                IrisSide::Bottom,
+                IrisSide::Left,
+                IrisSide::Right,
+            ] {
+                let position = match side {
+                    IrisSide::Top => UiRect {
+                        left: Val::Px(0.0),
+                        top: Val::Px(0.0),
+                        ..default()
+                    },
+                    IrisSide::Bottom => UiRect {
+                        left: Val::Px(0.0),
+                        bottom: Val::Px(0.0),
+                        ..default()
+                    },
+                    IrisSide::Left => UiRect {
+                        left: Val::Px(0.0),
+                        bottom: Val::Px(0.0),
+                        ..default()
+                    },
+                    IrisSide::Right => UiRect {
+                        right: Val::Px(0.0),
+                        bottom: Val::Px(0.0),
+                        ..default()
+                    },
+                };
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position,
+                            size: Size::new(Val::Px(0.0), Val::Px(0.0)),
+                            ..default()
+                        },
+                        background_color: Color::BLACK.into(),
+                        ..default()
+                    })
+                    .insert(side);
+            }
+        });
+}
+
+pub fn transition_system(
+    time: Res<Time>,
+    request: Option<Res<TransitionRequest>>,
+    transition: Option<ResMut<Transition>>,
+    state: Res<CurrentState<AppState>>,
+    loading_failed: Option<Res<LoadingFailed>>,
+    mut commands: Commands,
+) {
+    if let Some(request) = request {
+        commands.remove_resource::<TransitionRequest>();
+        if transition.is_none() {
+            commands.insert_resource(Transition::Out {
+                next: request.next,
+                level: request.level.clone(),
+                timer: Timer::from_seconds(Transition::DURATION, TimerMode::Once),
+            });
+            return;
+        }
+    }
+
+    let Some(mut transition) = transition else {
+        return;
+    };
+    match &mut *transition {
+        Transition::Out { next, level, timer } => {
+            if timer.tick(time.delta()).finished() {
+                if *next == AppState::Loading && state.0 != AppState::Loading {
+                    commands.insert_resource(LoadingFrom(state.0));
+                }
+                if let Some(level) = level.take() {
+                    level.apply(&mut commands);
+                }
+                commands.insert_resource(NextState(*next));
+                *transition = Transition::Hold;
+            }
+        }
+        Transition::Hold => {
+            // uncover once the level's spawned, or to show why it couldn't be
+            if state.0 != AppState::Loading || loading_failed.is_some() {
+                *transition = Transition::In {
+                    timer: Timer::from_seconds(
+                        Transition::DURATION,
+                        TimerMode::Once,
+                    ),
+                };
+            }
+        }
+        Transition::In { timer } => {
+            if timer.tick(time.delta()).finished() {
+                commands.remove_resource::<Transition>();
+            }
+        }
+    }
+}
+
+pub fn transition_overlay_system(
+    transition: Option<Res<Transition>>,
+    transition_style: Res<TransitionStyle>,
+    mut overlay_query: Query<&mut BackgroundColor, With<TransitionOverlay>>,
+    mut side_query: Query<(&IrisSide, &mut Style)>,
+) {
+    let coverage = transition.as_deref().map_or(0.0, Transition::coverage);
+
+    let fade = match *transition_style {
+        TransitionStyle::Fade => coverage,
+        TransitionStyle::Iris => 0.0,
+    };
+    for mut background_color in overlay_query.iter_mut() {
+        if background_color.0.a() != fade {
+            background_color.0 = Color::rgba(0.0, 0.0, 0.0, fade);
+        }
+    }
+
+    let iris = match *transition_style {
+        TransitionStyle::Fade => 0.0,
+        TransitionStyle::Iris => coverage * 50.0,
+    };
+    for (side, mut style) in side_query.iter_mut() {
+        let size = match side {
+            IrisSide::Top | IrisSide::Bottom => {
+                Size::new(Val::Percent(100.0), Val::Percent(iris))
+            }
+            IrisSide::Left | IrisSide::Right => {
+                Size::new(Val::Percent(iris), Val::Percent(100.0))
+            }
+        };
+        if style.size != size {
+            style.size = size;
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Builds the game without a window or rendering, out of only some of its
+// plugins, the way a tool or test would
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use platformer::death::{DeathCount, PlayState, PlayerDied};
+use platformer::lives::{Lives, LivesConfig};
+use platformer::platformer::AppState;
+use platformer::plugins::{InputPlugin, PhysicsPlugin, PlayerPlugin};
+
+#[test]
+fn runs_with_only_input_and_player_plugins() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(bevy::input::InputPlugin::default())
+        .add_plugin(InputPlugin)
+        .add_plugin(PlayerPlugin);
+
+    // PlayerPlugin orders its systems around the physics, so brings it along
+    assert!(app.is_plugin_added::<PhysicsPlugin>());
+
+    for _ in 0..10 {
+        app.update();
+    }
+    assert_eq!(
+        app.world.resource::<CurrentState<AppState>>().0,
+        AppState::MainMenu
+    );
+    assert_eq!(
+        app.world.resource::<CurrentState<PlayState>>().0,
+        PlayState::Playing
+    );
+}
+
+#[test]
+fn player_plugin_counts_deaths_and_takes_lives() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(LivesConfig {
+            starting_lives: Some(3),
+            max_lives: 9,
+        })
+        .add_plugin(PlayerPlugin);
+    // the guy's lives are given out at startup
+    app.update();
+    assert_eq!(*app.world.resource::<Lives>(), Lives(3));
+
+    app.world
+        .resource_mut::<Events<PlayerDied>>()
+        .send(PlayerDied);
+    app.update();
+    assert_eq!(app.world.resource::<DeathCount>().0, 1);
+    assert_eq!(*app.world.resource::<Lives>(), Lives(2));
+}